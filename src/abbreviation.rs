@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::location_finder::normalize_location_str;
+
+/// A normalized abbreviation and what it expands to, both as word lists.
+#[derive(Debug, Clone)]
+struct Abbreviation {
+    words: Vec<String>,
+    expansion: Vec<String>,
+}
+
+impl Abbreviation {
+    fn new(abbreviation: &str, expansion: &str) -> Abbreviation {
+        let split_words = |name: &str| {
+            normalize_location_str(name)
+                .split('_')
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_string())
+                .collect::<Vec<String>>()
+        };
+        Abbreviation {
+            words: split_words(abbreviation),
+            expansion: split_words(expansion),
+        }
+    }
+}
+
+/// Abbreviations expanded in city names before they are matched, e.g. "St."
+/// to "Saint" or "Ft" to "Fort". Entries can apply everywhere or only to one
+/// country, keyed by ISO2 code; a country entry wins over a global entry for
+/// the same words, so "St. Gallen" can expand to "Sankt Gallen" while "St.
+/// Louis" expands to "Saint Louis".
+#[derive(Debug, Clone)]
+pub struct AbbreviationTable {
+    global: Vec<Abbreviation>,
+    by_country: HashMap<String, Vec<Abbreviation>>,
+}
+
+impl Default for AbbreviationTable {
+    fn default() -> Self {
+        let mut abbreviation_table = AbbreviationTable::new();
+        abbreviation_table
+            .add("St", "Saint")
+            .add("Ste", "Sainte")
+            .add("Ft", "Fort")
+            .add("Mt", "Mount");
+        for country_code in ["DE", "AT", "CH"] {
+            abbreviation_table.add_for_country(country_code, "St", "Sankt");
+        }
+        abbreviation_table.add_for_country("DE", "a. M.", "am Main");
+        abbreviation_table
+    }
+}
+
+impl AbbreviationTable {
+    /// An empty table. Use [`AbbreviationTable::default`] for the built-in
+    /// entries.
+    pub fn new() -> AbbreviationTable {
+        AbbreviationTable {
+            global: Vec::new(),
+            by_country: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, abbreviation: &str, expansion: &str) -> &mut Self {
+        insert_abbreviation(&mut self.global, Abbreviation::new(abbreviation, expansion));
+        self
+    }
+
+    pub fn add_for_country(
+        &mut self,
+        country_code_iso2: &str,
+        abbreviation: &str,
+        expansion: &str,
+    ) -> &mut Self {
+        let country_abbreviations = self
+            .by_country
+            .entry(country_code_iso2.to_ascii_uppercase())
+            .or_default();
+        insert_abbreviation(
+            country_abbreviations,
+            Abbreviation::new(abbreviation, expansion),
+        );
+        self
+    }
+
+    /// Expands abbreviations in an already normalized name, in a single pass
+    /// so an expansion is never expanded again.
+    pub(crate) fn expand(&self, normalized_name: &str, country_code_iso2: Option<&str>) -> String {
+        let country_abbreviations = country_code_iso2
+            .and_then(|country_code| self.by_country.get(&country_code.to_ascii_uppercase()));
+        let words: Vec<&str> = normalized_name.split('_').collect();
+        let mut expanded_words: Vec<&str> = Vec::with_capacity(words.len());
+        let mut i = 0;
+        while i < words.len() {
+            let abbreviation = country_abbreviations
+                .into_iter()
+                .flatten()
+                .chain(self.global.iter())
+                .find(|abbreviation| {
+                    !abbreviation.words.is_empty()
+                        && abbreviation.words.len() <= words.len() - i
+                        && abbreviation
+                            .words
+                            .iter()
+                            .zip(&words[i..])
+                            .all(|(abbreviation_word, word)| abbreviation_word == word)
+                });
+            match abbreviation {
+                Some(abbreviation) => {
+                    expanded_words.extend(abbreviation.expansion.iter().map(|word| word.as_str()));
+                    i += abbreviation.words.len();
+                }
+                None => {
+                    expanded_words.push(words[i]);
+                    i += 1;
+                }
+            }
+        }
+        expanded_words.join("_")
+    }
+}
+
+/// Keeps longer abbreviations first so that "a m" is tried before "a".
+fn insert_abbreviation(abbreviations: &mut Vec<Abbreviation>, abbreviation: Abbreviation) {
+    abbreviations.retain(|existing| existing.words != abbreviation.words);
+    abbreviations.push(abbreviation);
+    abbreviations.sort_by_key(|abbreviation| std::cmp::Reverse(abbreviation.words.len()));
+}
@@ -3,14 +3,19 @@ use std::collections::HashMap;
 use clap::Parser;
 use location_finder::location_finder::{
     find_location, get_city_by_id, get_country_by_id, get_state_by_id, set_location_dataset_dir,
-    LocationMatchType,
+    set_partial_match_policy_file, try_init, LocationMatchType,
 };
+use location_finder::location_id::CityId;
 use log::{debug, info};
 
 #[derive(Parser, Debug)]
 struct Args {
     #[arg(long)]
     location_dataset_dir: Option<String>,
+    /// `ISO2|policy` lines applied on top of the built-in partial-match
+    /// policies, see `data/partial_match_policy.example.txt`.
+    #[arg(long)]
+    partial_match_policy: Option<String>,
     #[arg(long)]
     locations_to_map: String,
     #[arg(long)]
@@ -47,32 +52,44 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         info!("location_dataset_dir: {}", location_dataset_dir);
     }
     set_location_dataset_dir(args.location_dataset_dir);
+    set_partial_match_policy_file(args.partial_match_policy);
+    try_init()?;
 
     let mut reader = csv::Reader::from_path(args.locations_to_map)?;
     let mut location_records_total = 0;
     let mut location_records_full_match = 0;
+    let mut location_records_similar_state_match = 0;
     let mut location_records_partial_match = 0;
+    let mut location_records_ambiguous = 0;
 
-    let mut location_id_to_location_city_id: HashMap<u64, u64> = HashMap::new();
+    let mut location_id_to_location_city_id: HashMap<u64, CityId> = HashMap::new();
 
     let mut partial_match_locations: HashMap<String, u32> = HashMap::new();
     for location_input_record in reader.deserialize::<LocationInput>().flatten() {
         debug!("location_record: {:?}", location_input_record);
         location_records_total += 1;
-        let res = find_location(
+        let location_match = find_location(
             &location_input_record.city,
             &location_input_record.state,
             &location_input_record.country,
         )?;
-        match res {
+        if let LocationMatchType::Ambiguous { candidates } = &location_match {
+            debug!(
+                "Ambiguous match: {} candidates, using the tie-break",
+                candidates.len()
+            );
+            location_records_ambiguous += 1;
+        }
+        match location_match.resolve() {
             LocationMatchType::FullMatch {
                 city,
                 state,
                 country,
+                provenance,
             } => {
                 debug!(
-                    "Full match: city: {}, state: {}, country: {}",
-                    city, state, country
+                    "Full match: city: {}, state: {}, country: {}, provenance: {:?}",
+                    city, state, country, provenance
                 );
                 location_records_full_match += 1;
                 location_id_to_location_city_id.insert(location_input_record.id, city);
@@ -100,19 +117,65 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 location_records_partial_match += 1;
             }
-            LocationMatchType::NoMatch => {
+            LocationMatchType::SimilarStateMatch {
+                city,
+                state,
+                country,
+                similarity,
+                ..
+            } => {
+                debug!(
+                    "Similar state match: city: {}, state: {}, country: {}, similarity: {:.2}",
+                    city, state, country, similarity
+                );
+                location_records_similar_state_match += 1;
+                location_id_to_location_city_id.insert(location_input_record.id, city);
+            }
+            LocationMatchType::TokenSetMatch {
+                city,
+                state,
+                country,
+            } => {
+                debug!(
+                    "Token set match: city: {}, state: {}, country: {}",
+                    city, state, country
+                );
+            }
+            LocationMatchType::FuzzyMatch {
+                city,
+                state,
+                country,
+                distance,
+            } => {
+                debug!(
+                    "Fuzzy match: city: {}, state: {}, country: {}, distance: {}",
+                    city, state, country, distance
+                );
+            }
+            LocationMatchType::StateMatch { state, country } => {
+                debug!("State match: state: {}, country: {}", state, country);
+            }
+            LocationMatchType::CountryMatch { country } => {
+                debug!("Country match: country: {}", country);
+            }
+            LocationMatchType::Ambiguous { .. } | LocationMatchType::NoMatch => {
                 debug!("No match");
             }
         }
     }
 
+    let location_records_matched = location_records_full_match
+        + location_records_similar_state_match
+        + location_records_partial_match;
     info!(
-        "Total records: {}, matched records: {}, full matched records: {}, partial matches: {}, unmatched records: {}",
+        "Total records: {}, matched records: {}, full matched records: {}, similar state matches: {}, partial matches: {}, ambiguous (resolved by tie-break): {}, unmatched records: {}",
         location_records_total,
-        location_records_full_match + location_records_partial_match,
+        location_records_matched,
         location_records_full_match,
+        location_records_similar_state_match,
         location_records_partial_match,
-        location_records_total - (location_records_full_match + location_records_partial_match)
+        location_records_ambiguous,
+        location_records_total - location_records_matched
     );
 
     let mut count_vec: Vec<_> = partial_match_locations.iter().collect();
@@ -1,9 +1,85 @@
+use crate::location_id::PlaceId;
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum LocationFinderError {
     #[error("Error parsing CSV file")]
     CSV(#[from] csv::Error),
-    #[error("Error loading location records")]
-    Loader,
+    #[error("Duplicate location record {id} at {location}")]
+    DuplicateRecord {
+        location: SourceLocation,
+        id: PlaceId,
+    },
+    #[error("Error reading {location}")]
+    IO {
+        location: SourceLocation,
+        source: std::io::Error,
+    },
+    #[error("No {0} source was provided")]
+    MissingSource(&'static str),
+    #[error("Invalid place alias line at {location}: {line}")]
+    InvalidPlaceAlias {
+        location: SourceLocation,
+        line: String,
+    },
+    #[error("Invalid partial match policy line at {location}: {line}")]
+    InvalidPartialMatchPolicy {
+        location: SourceLocation,
+        line: String,
+    },
+    #[error("Found {} invalid location records", .0.len())]
+    InvalidRecords(Vec<InvalidRecord>),
+    #[error("Found {} dangling references in location records", .0.len())]
+    DanglingReferences(Vec<DanglingReference>),
+}
+
+/// A dataset input and, when known, the 1-based line within it.
+#[derive(Debug, Clone)]
+pub struct SourceLocation {
+    pub source_name: String,
+    pub line: Option<u64>,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.source_name, line),
+            None => write!(f, "{}", self.source_name),
+        }
+    }
+}
+
+/// A record whose foreign key (e.g. a city's `state_id`) names a record that
+/// does not exist in the dataset.
+#[derive(Debug, Clone)]
+pub struct DanglingReference {
+    pub location: SourceLocation,
+    pub record_id: PlaceId,
+    pub field: &'static str,
+    pub target_id: PlaceId,
+}
+
+impl fmt::Display for DanglingReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} has {} pointing to missing {}",
+            self.location, self.record_id, self.field, self.target_id
+        )
+    }
+}
+
+/// A CSV row that could not be read or did not match the record type, e.g. a
+/// non-numeric ID or a truncated row.
+#[derive(Debug, Clone)]
+pub struct InvalidRecord {
+    pub location: SourceLocation,
+    pub message: String,
+}
+
+impl fmt::Display for InvalidRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
 }
@@ -0,0 +1,207 @@
+use serde::Serialize;
+
+use crate::error::LocationFinderError;
+use crate::location_finder::{LocationIndex, LocationMatchType, PlaceAliasEntry};
+use crate::location_id::{CityId, CountryId, PlaceId, StateId};
+use crate::location_key::NormalizedKey;
+use crate::location_query::LocationQuery;
+use crate::partial_match_policy::PartialMatchPolicy;
+
+/// A step of the matcher, in the order they are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStage {
+    /// City, state and country key.
+    FullKey,
+    /// City and country key, with the state checked afterwards.
+    CityCountryKey,
+    TokenSet,
+    Fuzzy,
+    State,
+    Country,
+}
+
+/// The query after normalization, as it was used to build keys.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NormalizedInput {
+    pub city: String,
+    pub state: String,
+    pub country: String,
+}
+
+/// One lookup in a name index and the record IDs it returned.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyLookup {
+    pub stage: MatchStage,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    pub ids: Vec<PlaceId>,
+}
+
+/// The candidates a stage produced: cities for the city stages, states for
+/// the state stage and countries for the country stage.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageCandidates {
+    pub stage: MatchStage,
+    pub ids: Vec<PlaceId>,
+}
+
+/// A rule that changed how the input was read or how a candidate was
+/// judged.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum MatchRule {
+    /// No country was given, so the query's default country was used.
+    DefaultCountry { country: String },
+    /// No country was given, so all countries were searched.
+    CountryMissing,
+    /// The country input was an ISO 3166-1 numeric code.
+    NumericCountryCode { code: u32, country: CountryId },
+    /// The state input was an ISO 3166-2 subdivision code such as "US-CA".
+    SubdivisionCode { code: String, state: StateId },
+    /// No state was given, so the city matched on city and country alone.
+    StateMissing { city: CityId },
+    /// Abbreviations in the city input were expanded.
+    AbbreviationExpanded { from: String, to: String },
+    /// The key that matched the city was generated from this
+    /// `place_alias.txt` entry.
+    PlaceAlias {
+        city: CityId,
+        alias: PlaceAliasEntry,
+    },
+    /// The state did not match and the country's policy decided what to do.
+    PartialMatchPolicy {
+        city: CityId,
+        country_code: String,
+        policy: PartialMatchPolicy,
+    },
+    /// The state similarity check ran for a city whose state did not match.
+    StateSimilarity {
+        city: CityId,
+        state: StateId,
+        similarity: f64,
+        accepted: bool,
+    },
+    /// The query hints narrowed tied candidates down to `kept`.
+    HintTieBreak {
+        candidates: Vec<PlaceId>,
+        kept: Vec<PlaceId>,
+    },
+}
+
+/// How [`LocationIndex::explain_location`] reached its decision.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationExplanation {
+    pub query: LocationQuery,
+    pub normalized: NormalizedInput,
+    pub keys_tried: Vec<KeyLookup>,
+    pub stages: Vec<StageCandidates>,
+    pub rules: Vec<MatchRule>,
+    pub decision: LocationMatchType,
+}
+
+/// Collects the steps of one lookup. A disabled trace, used by the plain
+/// lookup functions, records nothing.
+#[derive(Default)]
+pub(crate) struct LocationTrace {
+    enabled: bool,
+    normalized: Option<NormalizedInput>,
+    keys_tried: Vec<KeyLookup>,
+    stages: Vec<StageCandidates>,
+    rules: Vec<MatchRule>,
+}
+
+impl LocationTrace {
+    fn enabled() -> LocationTrace {
+        LocationTrace {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn normalized_input(&mut self, city: &str, state: &str, country: &str) {
+        if self.enabled {
+            self.normalized = Some(NormalizedInput {
+                city: city.to_string(),
+                state: state.to_string(),
+                country: country.to_string(),
+            });
+        }
+    }
+
+    pub(crate) fn key_tried<I: Copy + Into<PlaceId>>(
+        &mut self,
+        stage: MatchStage,
+        normalized_key: &NormalizedKey,
+        ids: Option<&Vec<I>>,
+    ) {
+        if self.enabled {
+            self.keys_tried.push(KeyLookup {
+                stage,
+                city: normalized_key.city.clone(),
+                state: normalized_key.state.clone(),
+                country: normalized_key.country.clone(),
+                ids: ids.into_iter().flatten().map(|id| (*id).into()).collect(),
+            });
+        }
+    }
+
+    pub(crate) fn stage_candidates<'a>(
+        &mut self,
+        stage: MatchStage,
+        matches: impl IntoIterator<Item = &'a LocationMatchType>,
+    ) {
+        if self.enabled {
+            self.stages.push(StageCandidates {
+                stage,
+                ids: matches.into_iter().filter_map(match_place_id).collect(),
+            });
+        }
+    }
+
+    /// Records a rule; `rule` is only called when the trace is enabled and
+    /// may return `None` when the rule turns out not to apply.
+    pub(crate) fn rule_fired<R: Into<Option<MatchRule>>>(&mut self, rule: impl FnOnce() -> R) {
+        if self.enabled {
+            self.rules.extend(rule().into());
+        }
+    }
+}
+
+/// The most specific record in a match, which may be a city, state or
+/// country.
+pub(crate) fn match_place_id(location_match: &LocationMatchType) -> Option<PlaceId> {
+    match location_match {
+        LocationMatchType::FullMatch { city, .. }
+        | LocationMatchType::SimilarStateMatch { city, .. }
+        | LocationMatchType::PartialMatch { city, .. }
+        | LocationMatchType::TokenSetMatch { city, .. }
+        | LocationMatchType::FuzzyMatch { city, .. } => Some(PlaceId::City(*city)),
+        LocationMatchType::StateMatch { state, .. } => Some(PlaceId::State(*state)),
+        LocationMatchType::CountryMatch { country } => Some(PlaceId::Country(*country)),
+        LocationMatchType::Ambiguous { .. } | LocationMatchType::NoMatch => None,
+    }
+}
+
+impl LocationIndex {
+    /// Runs [`LocationIndex::find_location_query`] and reports every step
+    /// it took: the normalized input, each key looked up with the IDs it
+    /// returned, the candidates of each stage that ran, the rules that fired
+    /// and the final decision.
+    pub fn explain_location(
+        &self,
+        query: &LocationQuery,
+    ) -> Result<LocationExplanation, LocationFinderError> {
+        let mut trace = LocationTrace::enabled();
+        let decision = self.find_location_query_traced(query, &mut trace)?;
+        Ok(LocationExplanation {
+            query: query.clone(),
+            normalized: trace.normalized.unwrap_or_default(),
+            keys_tried: trace.keys_tried,
+            stages: trace.stages,
+            rules: trace.rules,
+            decision,
+        })
+    }
+}
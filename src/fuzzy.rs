@@ -0,0 +1,101 @@
+/// Levenshtein edit distance between two strings, counted in chars.
+pub fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev_row: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut row: Vec<usize> = vec![0; b_chars.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        row[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            row[j + 1] = (prev_row[j] + substitution_cost)
+                .min(prev_row[j + 1] + 1)
+                .min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev_row, &mut row);
+    }
+    prev_row[b_chars.len()]
+}
+
+struct BkNode<T> {
+    key: String,
+    values: Vec<T>,
+    children: Vec<(usize, usize)>,
+}
+
+/// A BK-tree over string keys using Levenshtein distance. Lookups only visit
+/// subtrees that can contain keys within the requested distance, so they stay
+/// well below a linear scan for small distances.
+pub struct BkTree<T> {
+    nodes: Vec<BkNode<T>>,
+}
+
+impl<T> Default for BkTree<T> {
+    fn default() -> Self {
+        BkTree { nodes: Vec::new() }
+    }
+}
+
+impl<T: PartialEq> BkTree<T> {
+    pub fn insert(&mut self, key: &str, value: T) {
+        if self.nodes.is_empty() {
+            self.nodes.push(BkNode {
+                key: key.to_string(),
+                values: vec![value],
+                children: Vec::new(),
+            });
+            return;
+        }
+        let mut node_index = 0;
+        loop {
+            let distance = levenshtein_distance(&self.nodes[node_index].key, key);
+            if distance == 0 {
+                let values = &mut self.nodes[node_index].values;
+                if !values.contains(&value) {
+                    values.push(value);
+                }
+                return;
+            }
+            let child_index = self.nodes[node_index]
+                .children
+                .iter()
+                .find(|(child_distance, _)| *child_distance == distance)
+                .map(|(_, child_index)| *child_index);
+            match child_index {
+                Some(child_index) => node_index = child_index,
+                None => {
+                    let new_index = self.nodes.len();
+                    self.nodes.push(BkNode {
+                        key: key.to_string(),
+                        values: vec![value],
+                        children: Vec::new(),
+                    });
+                    self.nodes[node_index].children.push((distance, new_index));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns every key within `max_distance` of `key` with its distance and
+    /// values.
+    pub fn find(&self, key: &str, max_distance: usize) -> Vec<(usize, &str, &[T])> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let mut pending = vec![0];
+        while let Some(node_index) = pending.pop() {
+            let node = &self.nodes[node_index];
+            let distance = levenshtein_distance(&node.key, key);
+            if distance <= max_distance {
+                found.push((distance, node.key.as_str(), node.values.as_slice()));
+            }
+            for (child_distance, child_index) in &node.children {
+                if child_distance.abs_diff(distance) <= max_distance {
+                    pending.push(*child_index);
+                }
+            }
+        }
+        found
+    }
+}
@@ -1,2 +1,14 @@
+pub mod abbreviation;
 pub mod error;
+pub mod explain;
+mod fuzzy;
 pub mod location_finder;
+pub mod location_id;
+mod location_key;
+pub mod location_parser;
+pub mod location_query;
+pub mod matched_location;
+pub mod partial_match_policy;
+pub mod spatial;
+mod transliterate;
+mod trie;
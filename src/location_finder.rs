@@ -1,19 +1,33 @@
-use crate::error::LocationFinderError;
+use crate::abbreviation::AbbreviationTable;
+use crate::error::{DanglingReference, InvalidRecord, LocationFinderError, SourceLocation};
+use crate::explain::{LocationExplanation, LocationTrace, MatchRule, MatchStage};
+use crate::fuzzy::{levenshtein_distance, BkTree};
+use crate::location_id::{CityId, CountryId, PlaceId, StateId};
+use crate::location_key::{LocationKey, NameInterner, NormalizedKey};
+use crate::location_parser::LocationInterpretation;
+use crate::location_query::LocationQuery;
+use crate::matched_location::MatchedLocation;
+use crate::partial_match_policy::{PartialMatchPolicies, PartialMatchPolicy};
+use crate::spatial::{CityDistance, SpatialIndex};
+use crate::transliterate::{fold_latin_char, transliterate_char};
+use crate::trie::PrefixTrie;
 use log::{debug, error, info};
 use multimap::MultiMap;
 use serde::de::DeserializeOwned;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     fs::File,
-    io::{self, BufRead},
+    hash::Hash,
+    io::{self, BufRead, Read},
+    path::Path,
     sync::OnceLock,
     vec,
 };
 use unicode_normalization::UnicodeNormalization;
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct LocationCountry {
-    pub id: u64,
+    pub id: CountryId,
     pub name: String,
     pub iso3: String,
     pub iso2: String,
@@ -35,11 +49,11 @@ pub struct LocationCountry {
     pub emoji_u: String,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct LocationState {
-    pub id: u64,
+    pub id: StateId,
     pub name: String,
-    pub country_id: u64,
+    pub country_id: CountryId,
     pub country_code: String,
     pub country_name: String,
     pub state_code: String,
@@ -49,14 +63,14 @@ pub struct LocationState {
     pub longitude: Option<f64>,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct LocationCity {
-    pub id: u64,
+    pub id: CityId,
     pub name: String,
-    pub state_id: u64,
+    pub state_id: StateId,
     pub state_code: String,
     pub state_name: String,
-    pub country_id: u64,
+    pub country_id: CountryId,
     pub country_code: String,
     pub country_name: String,
     pub latitude: Option<f64>,
@@ -66,12 +80,14 @@ pub struct LocationCity {
 }
 
 trait LocationBase {
-    fn id(&self) -> u64;
+    type Id: Copy + Eq + Hash + Into<PlaceId>;
+    fn id(&self) -> Self::Id;
     fn name(&self) -> &str;
 }
 
 impl LocationBase for LocationCity {
-    fn id(&self) -> u64 {
+    type Id = CityId;
+    fn id(&self) -> CityId {
         self.id
     }
     fn name(&self) -> &str {
@@ -80,7 +96,8 @@ impl LocationBase for LocationCity {
 }
 
 impl LocationBase for LocationCountry {
-    fn id(&self) -> u64 {
+    type Id = CountryId;
+    fn id(&self) -> CountryId {
         self.id
     }
     fn name(&self) -> &str {
@@ -89,7 +106,8 @@ impl LocationBase for LocationCountry {
 }
 
 impl LocationBase for LocationState {
-    fn id(&self) -> u64 {
+    type Id = StateId;
+    fn id(&self) -> StateId {
         self.id
     }
     fn name(&self) -> &str {
@@ -110,61 +128,382 @@ pub fn set_location_dataset_dir(location_dataset_dir: Option<String>) {
     }
 }
 
-static CITY_ID_MAP: OnceLock<HashMap<u64, LocationCity>> = OnceLock::new();
-fn init_city_id_map() -> HashMap<u64, LocationCity> {
+const PLACE_ALIAS_FILENAME: &str = "./data/place_alias.txt";
+
+static PARTIAL_MATCH_POLICY_FILENAME: OnceLock<String> = OnceLock::new();
+/// Sets a partial-match policy file for the process-wide index, applied on
+/// top of the built-in policies. Must be called before the index is loaded.
+pub fn set_partial_match_policy_file(partial_match_policy_filename: Option<String>) {
+    if let Some(partial_match_policy_filename) = partial_match_policy_filename {
+        PARTIAL_MATCH_POLICY_FILENAME
+            .set(partial_match_policy_filename.clone())
+            .expect("Failed to set partial match policy file");
+        info!(
+            "Loading partial match policies from: {}",
+            partial_match_policy_filename
+        );
+    }
+}
+
+static LOCATION_INDEX: OnceLock<LocationIndex> = OnceLock::new();
+
+/// Loads the process-wide index from the configured dataset directory if it
+/// has not been loaded yet, with the built-in partial-match policies and the
+/// file given to [`set_partial_match_policy_file`], if any. Call this at
+/// startup to surface bad reference data as an error rather than a panic on
+/// first lookup.
+pub fn try_init() -> Result<&'static LocationIndex, LocationFinderError> {
+    if let Some(location_index) = LOCATION_INDEX.get() {
+        return Ok(location_index);
+    }
     let location_dataset_dir = LOCATION_DATASET_DIR.get_or_init(init_location_dataset_dir);
-    load_records_by_id(format!("{}/cities.csv", location_dataset_dir).as_str())
-        .expect("Failed to load countries")
+    let mut location_index_builder = LocationIndexBuilder::new()
+        .dataset_dir(location_dataset_dir)?
+        .place_alias_file(PLACE_ALIAS_FILENAME)?;
+    if let Some(partial_match_policy_filename) = PARTIAL_MATCH_POLICY_FILENAME.get() {
+        location_index_builder =
+            location_index_builder.partial_match_policy_file(partial_match_policy_filename)?;
+    }
+    let location_index = location_index_builder.build()?;
+    Ok(LOCATION_INDEX.get_or_init(|| location_index))
+}
+
+/// Returns the process-wide index loaded from the configured dataset directory.
+///
+/// Panics if the dataset fails to load; use [`try_init`] first to handle that.
+pub fn location_index() -> &'static LocationIndex {
+    try_init().expect("Failed to load location index")
 }
-pub fn get_city_by_id(id: u64) -> Option<&'static LocationCity> {
-    CITY_ID_MAP.get_or_init(init_city_id_map).get(&id)
+
+pub fn get_city_by_id(id: CityId) -> Option<&'static LocationCity> {
+    location_index().get_city_by_id(id)
 }
 
-static STATE_ID_MAP: OnceLock<HashMap<u64, LocationState>> = OnceLock::new();
-fn init_state_id_map() -> HashMap<u64, LocationState> {
-    let location_dataset_dir = LOCATION_DATASET_DIR.get_or_init(init_location_dataset_dir);
-    load_records_by_id(format!("{}/states.csv", location_dataset_dir).as_str())
-        .expect("Failed to load states")
+pub fn get_state_by_id(id: StateId) -> Option<&'static LocationState> {
+    location_index().get_state_by_id(id)
+}
+
+pub fn get_city_by_wikidata(wikidata_id: &str) -> Option<&'static LocationCity> {
+    location_index().get_city_by_wikidata(wikidata_id)
 }
-pub fn get_state_by_id(id: u64) -> Option<&'static LocationState> {
-    STATE_ID_MAP.get_or_init(init_state_id_map).get(&id)
+
+pub fn wikidata_report() -> WikidataReport {
+    location_index().wikidata_report()
 }
 
-static COUNTRY_ID_MAP: OnceLock<HashMap<u64, LocationCountry>> = OnceLock::new();
-fn init_country_id_map() -> HashMap<u64, LocationCountry> {
-    let location_dataset_dir = LOCATION_DATASET_DIR.get_or_init(init_location_dataset_dir);
-    load_records_by_id(format!("{}/countries.csv", location_dataset_dir).as_str())
-        .expect("Failed to load countries")
+pub fn get_country_by_id(id: CountryId) -> Option<&'static LocationCountry> {
+    location_index().get_country_by_id(id)
+}
+
+pub fn find_state_by_iso3166_2(code: &str) -> Option<&'static LocationState> {
+    location_index().find_state_by_iso3166_2(code)
+}
+
+pub fn find_country_by_numeric(numeric_code: u32) -> Option<&'static LocationCountry> {
+    location_index().find_country_by_numeric(numeric_code)
+}
+
+pub fn find_country_by_iso2(country_code_iso2: &str) -> Option<&'static LocationCountry> {
+    location_index().find_country_by_iso2(country_code_iso2)
+}
+
+pub fn find_country_by_iso3(country_code_iso3: &str) -> Option<&'static LocationCountry> {
+    location_index().find_country_by_iso3(country_code_iso3)
+}
+
+pub fn countries() -> impl Iterator<Item = &'static LocationCountry> {
+    location_index().countries()
+}
+
+pub fn states() -> impl Iterator<Item = &'static LocationState> {
+    location_index().states()
+}
+
+pub fn cities() -> impl Iterator<Item = &'static LocationCity> {
+    location_index().cities()
+}
+
+pub fn states_of(country_id: CountryId) -> impl Iterator<Item = &'static LocationState> {
+    location_index().states_of(country_id)
+}
+
+pub fn cities_of(state_id: StateId) -> impl Iterator<Item = &'static LocationCity> {
+    location_index().cities_of(state_id)
+}
+
+pub fn cities_of_country(country_id: CountryId) -> impl Iterator<Item = &'static LocationCity> {
+    location_index().cities_of_country(country_id)
+}
+
+pub fn parent_state(city_id: CityId) -> Option<&'static LocationState> {
+    location_index().parent_state(city_id)
+}
+
+pub fn find_location(
+    city_in: &str,
+    state_in: &str,
+    country_in: &str,
+) -> Result<LocationMatchType, LocationFinderError> {
+    location_index().find_location(city_in, state_in, country_in)
+}
+
+pub fn find_location_candidates(
+    city_in: &str,
+    state_in: &str,
+    country_in: &str,
+    limit: usize,
+) -> Vec<LocationCandidate> {
+    location_index().find_location_candidates(city_in, state_in, country_in, limit)
+}
+
+pub fn find_location_query(
+    query: &LocationQuery,
+) -> Result<LocationMatchType, LocationFinderError> {
+    location_index().find_location_query(query)
+}
+
+pub fn explain_location(query: &LocationQuery) -> Result<LocationExplanation, LocationFinderError> {
+    location_index().explain_location(query)
+}
+
+pub fn matched_location(location_match: &LocationMatchType) -> Option<MatchedLocation> {
+    location_index().matched_location(location_match)
+}
+
+pub fn find_location_str(location_str: &str) -> Option<LocationInterpretation> {
+    location_index().find_location_str(location_str)
+}
+
+pub fn nearest_city(latitude: f64, longitude: f64) -> Option<CityDistance> {
+    location_index().nearest_city(latitude, longitude)
+}
+
+pub fn cities_within(latitude: f64, longitude: f64, radius_km: f64) -> Vec<CityDistance> {
+    location_index().cities_within(latitude, longitude, radius_km)
+}
+
+/// Records loaded from one CSV source, along with the line each record came
+/// from so that later validation can point back at the input.
+struct LoadedRecords<T: LocationBase> {
+    source_name: String,
+    id_map: HashMap<T::Id, T>,
+    line_map: HashMap<T::Id, u64>,
 }
-pub fn get_country_by_id(id: u64) -> Option<&'static LocationCountry> {
-    COUNTRY_ID_MAP.get_or_init(init_country_id_map).get(&id)
+
+impl<T: LocationBase> LoadedRecords<T> {
+    fn location(&self, id: T::Id) -> SourceLocation {
+        SourceLocation {
+            source_name: self.source_name.clone(),
+            line: self.line_map.get(&id).copied(),
+        }
+    }
 }
 
 fn load_records_by_id<T: Clone + std::fmt::Debug + LocationBase + DeserializeOwned>(
-    filename: &str,
-) -> Result<HashMap<u64, T>, LocationFinderError> {
-    let mut id_map: HashMap<u64, T> = HashMap::new();
-    let mut reader = csv::Reader::from_path(filename)?;
-    for result in reader.deserialize::<T>() {
-        if let Ok(location_record) = result {
-            let prev_record = id_map.insert(location_record.id(), location_record.clone());
-            if prev_record.is_some() {
-                error!("Duplicate location record: {:?}", location_record);
-                return Err(LocationFinderError::Loader);
-            }
-        } else {
-            error!(
-                "Error processing location record: {}",
-                result.err().unwrap()
-            );
+    source: LocationSource,
+) -> Result<LoadedRecords<T>, LocationFinderError> {
+    let filename = source.name;
+    let mut id_map: HashMap<T::Id, T> = HashMap::new();
+    let mut line_map: HashMap<T::Id, u64> = HashMap::new();
+    let mut reader = csv::Reader::from_reader(source.reader);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(err) => return Err(header_error(&filename, err)),
+    };
+    let mut invalid_records = Vec::new();
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => {
+                invalid_records.push(InvalidRecord {
+                    location: SourceLocation {
+                        source_name: filename.clone(),
+                        line: err.position().map(|position| position.line()),
+                    },
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line());
+        match record.deserialize::<T>(Some(&headers)) {
+            Ok(location_record) => {
+                let id = location_record.id();
+                if id_map.insert(id, location_record).is_some() {
+                    return Err(LocationFinderError::DuplicateRecord {
+                        location: SourceLocation {
+                            source_name: filename,
+                            line: Some(line),
+                        },
+                        id: id.into(),
+                    });
+                }
+                line_map.insert(id, line);
+            }
+            Err(err) => {
+                invalid_records.push(InvalidRecord {
+                    location: SourceLocation {
+                        source_name: filename.clone(),
+                        line: Some(line),
+                    },
+                    message: err.to_string(),
+                });
+            }
+        }
+    }
+    if !invalid_records.is_empty() {
+        for invalid_record in &invalid_records {
+            error!("{}", invalid_record);
         }
+        return Err(LocationFinderError::InvalidRecords(invalid_records));
     }
     info!("Loaded {} location records from {}", id_map.len(), filename);
-    Ok(id_map)
+    Ok(LoadedRecords {
+        source_name: filename,
+        id_map,
+        line_map,
+    })
+}
+
+/// Turns a failure to read a CSV header row into an error that names the
+/// source: an I/O error stays one, anything else (e.g. invalid UTF-8) is
+/// reported like an invalid record.
+fn header_error(filename: &str, err: csv::Error) -> LocationFinderError {
+    let location = SourceLocation {
+        source_name: filename.to_string(),
+        line: err.position().map(|position| position.line()),
+    };
+    let message = err.to_string();
+    match err.into_kind() {
+        csv::ErrorKind::Io(io_err) => LocationFinderError::IO {
+            location,
+            source: io_err,
+        },
+        _ => {
+            let invalid_record = InvalidRecord { location, message };
+            error!("{}", invalid_record);
+            LocationFinderError::InvalidRecords(vec![invalid_record])
+        }
+    }
+}
+
+fn find_dangling_references(
+    countries: &LoadedRecords<LocationCountry>,
+    states: &LoadedRecords<LocationState>,
+    cities: &LoadedRecords<LocationCity>,
+) -> Vec<DanglingReference> {
+    let mut dangling_references = Vec::new();
+    for state_record in states.id_map.values() {
+        if !countries.id_map.contains_key(&state_record.country_id) {
+            dangling_references.push(DanglingReference {
+                location: states.location(state_record.id),
+                record_id: state_record.id.into(),
+                field: "country_id",
+                target_id: state_record.country_id.into(),
+            });
+        }
+    }
+    for city_record in cities.id_map.values() {
+        if !states.id_map.contains_key(&city_record.state_id) {
+            dangling_references.push(DanglingReference {
+                location: cities.location(city_record.id),
+                record_id: city_record.id.into(),
+                field: "state_id",
+                target_id: city_record.state_id.into(),
+            });
+        }
+        if !countries.id_map.contains_key(&city_record.country_id) {
+            dangling_references.push(DanglingReference {
+                location: cities.location(city_record.id),
+                record_id: city_record.id.into(),
+                field: "country_id",
+                target_id: city_record.country_id.into(),
+            });
+        }
+    }
+    dangling_references.sort_by(|a, b| {
+        (&a.location.source_name, a.location.line).cmp(&(&b.location.source_name, b.location.line))
+    });
+    dangling_references
+}
+
+fn normalize_wikidata_id(wikidata_id: &str) -> String {
+    wikidata_id.trim().to_ascii_uppercase()
+}
+
+/// Groups child IDs by parent ID, each group sorted by ID.
+fn build_child_id_map<P: Eq + Hash, C: Ord>(
+    parent_child_ids: impl Iterator<Item = (P, C)>,
+) -> HashMap<P, Vec<C>> {
+    let mut child_id_map: HashMap<P, Vec<C>> = HashMap::new();
+    for (parent_id, child_id) in parent_child_ids {
+        child_id_map.entry(parent_id).or_default().push(child_id);
+    }
+    for child_ids in child_id_map.values_mut() {
+        child_ids.sort_unstable();
+    }
+    child_id_map
+}
+
+/// Maps each code to the lowest ID that has it, skipping empty codes.
+fn build_code_id_map<K: Eq + Hash, I: Ord + Copy>(
+    code_ids: impl Iterator<Item = (K, I)>,
+) -> HashMap<K, I> {
+    let mut code_id_map: HashMap<K, I> = HashMap::new();
+    for (code, id) in code_ids {
+        code_id_map
+            .entry(code)
+            .and_modify(|code_id| *code_id = (*code_id).min(id))
+            .or_insert(id);
+    }
+    code_id_map
+}
+
+fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+fn sort_name_ids<I: Ord>(name_map: &mut MultiMap<LocationKey, I>) {
+    for (_, ids) in name_map.iter_all_mut() {
+        ids.sort_unstable();
+    }
+}
+
+/// Characters that separate words inside a place name, e.g. the hyphens in
+/// "Pays-de-la-Loire" or the apostrophe in "Côte-d’Azur". They are treated
+/// like spaces so that "Pays-de-la-Loire" and "Pays de la Loire" share a key.
+fn is_word_separator(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '\u{2010}'..='\u{2015}' // hyphens and dashes
+            | '/'
+            | '.'
+            | '\''
+            | '`'
+            | '\u{00B4}' // acute accent
+            | '\u{02BC}' // modifier letter apostrophe
+            | '\u{2018}'..='\u{201B}' // single quotation marks
+    )
 }
 
+/// Lowercases, romanizes supported non-Latin scripts, folds Latin letters
+/// without a decomposition (ł, ø, ß, ...), splits words on hyphens, slashes,
+/// dots and apostrophes, strips accents and other punctuation and joins the
+/// remaining words with `_`.
 pub fn normalize_location_str(location_str: &str) -> String {
     location_str
+        .chars()
+        .fold(String::new(), |mut transliterated, c| {
+            if is_word_separator(c) {
+                transliterated.push(' ');
+                return transliterated;
+            }
+            match transliterate_char(c).or_else(|| fold_latin_char(c)) {
+                Some(romanized) => transliterated.push_str(romanized),
+                None => transliterated.push(c),
+            }
+            transliterated
+        })
         .nfkd()
         .filter(|c| c.is_ascii() && !c.is_ascii_punctuation() && !c.is_ascii_control())
         .collect::<String>()
@@ -174,342 +513,2412 @@ pub fn normalize_location_str(location_str: &str) -> String {
         .to_lowercase()
 }
 
-pub fn location_key(
+fn location_key(
     normalized_city: Option<&str>,
     normalized_state: Option<&str>,
     normalized_country: Option<&str>,
-) -> String {
-    let mut key_parts: Vec<&str> = Vec::new();
-    if let Some(normalized_city) = normalized_city {
-        key_parts.push(normalized_city);
-    }
-    if let Some(normalized_state) = normalized_state {
-        key_parts.push(normalized_state);
-    }
-    if let Some(normalized_country) = normalized_country {
-        key_parts.push(normalized_country);
-    }
-    key_parts.join("_")
+) -> NormalizedKey {
+    NormalizedKey::new(normalized_city, normalized_state, normalized_country)
+}
+
+/// An alternative name from `place_alias.txt` and the line that defined it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PlaceAliasEntry {
+    /// The alias, e.g. "München, Bayern, Germany".
+    pub name: String,
+    /// The 1-based line number in the alias file.
+    pub line_number: u64,
+    pub line: String,
 }
 
-static PLACE_ALIAS_MAP: OnceLock<MultiMap<String, String>> = OnceLock::new();
-fn init_place_alias_map() -> MultiMap<String, String> {
+fn load_place_alias_map(
+    source: LocationSource,
+) -> Result<MultiMap<String, PlaceAliasEntry>, LocationFinderError> {
     let mut place_alias_map = MultiMap::new();
-    let place_alias_file = File::open("./data/place_alias.txt").unwrap();
-    let buf_reader = io::BufReader::new(place_alias_file);
-    for line in buf_reader.lines() {
-        let line = line.unwrap();
-        let line_vec: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
-        if line_vec.len() == 2 {
-            let place_vec: Vec<&str> = line_vec[0].split(',').map(|s| s.trim()).collect();
-            let alias_vec: Vec<&str> = line_vec[1].split(',').map(|s| s.trim()).collect();
-            if place_vec.len() != alias_vec.len() {
-                error!("Invalid place alias line: {}", line);
-                continue;
+    let buf_reader = io::BufReader::new(source.reader);
+    for (line_index, line) in buf_reader.lines().enumerate() {
+        let location = SourceLocation {
+            source_name: source.name.clone(),
+            line: Some(line_index as u64 + 1),
+        };
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                return Err(LocationFinderError::IO {
+                    location,
+                    source: err,
+                })
             }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_vec: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
+        if line_vec.len() != 2 {
+            return Err(LocationFinderError::InvalidPlaceAlias { location, line });
+        }
+        let place_vec: Vec<&str> = line_vec[0].split(',').map(|s| s.trim()).collect();
+        let alias_vec: Vec<&str> = line_vec[1].split(',').map(|s| s.trim()).collect();
+        if place_vec.len() != alias_vec.len() {
+            return Err(LocationFinderError::InvalidPlaceAlias { location, line });
+        }
 
-            for i in 0..place_vec.len() {
-                if place_vec[i] == alias_vec[i] {
-                    continue;
-                }
-                let place_key = &place_vec[i..].join(", ");
-                let alias_key = &alias_vec[i..].join(", ");
-                place_alias_map.insert(place_key.to_string(), alias_key.to_string());
+        for i in 0..place_vec.len() {
+            if place_vec[i] == alias_vec[i] {
+                continue;
             }
+            let place_key = &place_vec[i..].join(", ");
+            let alias_key = &alias_vec[i..].join(", ");
+            place_alias_map.insert(
+                place_key.to_string(),
+                PlaceAliasEntry {
+                    name: alias_key.to_string(),
+                    line_number: line_index as u64 + 1,
+                    line: line.clone(),
+                },
+            );
         }
     }
-    place_alias_map
-}
-
-fn find_alias_city_names(city_record: &LocationCity) -> Option<&Vec<String>> {
-    let alias_place_lookup_key = format!(
-        "{}, {}, {}",
-        city_record.name, city_record.state_name, city_record.country_name
+    info!(
+        "Loaded {} place aliases from {}",
+        place_alias_map.len(),
+        source.name
     );
-    PLACE_ALIAS_MAP
-        .get_or_init(init_place_alias_map)
-        .get_vec(alias_place_lookup_key.as_str())
-}
-fn find_alias_state_names(state_record: &LocationState) -> Option<&Vec<String>> {
-    let alias_place_lookup_key = format!("{}, {}", state_record.name, state_record.country_name);
-    PLACE_ALIAS_MAP
-        .get_or_init(init_place_alias_map)
-        .get_vec(alias_place_lookup_key.as_str())
-}
-
-fn list_city_location_keys(
-    city_record: &LocationCity,
-    city_alias: Option<&str>,
-    state_alias: Option<&str>,
-) -> Vec<String> {
-    let mut location_keys = Vec::new();
-    let city_name = normalize_location_str(city_alias.unwrap_or(city_record.name()));
-    let state_name = normalize_location_str(state_alias.unwrap_or(&city_record.state_name));
-    let country_name = normalize_location_str(&city_record.country_name);
-    location_keys.push(location_key(
-        Some(&city_name),
-        Some(&state_name),
-        Some(&country_name),
-    ));
-    location_keys.push(location_key(Some(&city_name), None, Some(&country_name)));
-    let state_record = get_state_by_id(city_record.state_id).unwrap();
-    let state_code = normalize_location_str(&state_record.state_code);
-    location_keys.push(location_key(
-        Some(&city_name),
-        Some(&state_code),
-        Some(&country_name),
-    ));
-    let country_record = get_country_by_id(city_record.country_id).unwrap();
-    let country_code_iso2 = normalize_location_str(&country_record.iso2);
-    location_keys.push(location_key(
-        Some(&city_name),
-        Some(&state_code),
-        Some(&country_code_iso2),
-    ));
-    location_keys.push(location_key(
-        Some(&city_name),
-        None,
-        Some(&country_code_iso2),
-    ));
-    let country_code_iso3 = normalize_location_str(&country_record.iso3);
-    location_keys.push(location_key(
-        Some(&city_name),
-        Some(&state_code),
-        Some(&country_code_iso3),
-    ));
-    location_keys.push(location_key(
-        Some(&city_name),
-        None,
-        Some(&country_code_iso3),
-    ));
-    location_keys
-}
-
-static CITY_NAME_MAP: OnceLock<MultiMap<String, u64>> = OnceLock::new();
-fn init_city_name_map() -> MultiMap<String, u64> {
-    let city_id_map = CITY_ID_MAP.get_or_init(init_city_id_map);
-    city_id_map
-        .values()
-        .fold(MultiMap::new(), |mut city_name_map, city_record| {
-            let mut location_keys_set: HashSet<String> =
-                list_city_location_keys(city_record, None, None)
-                    .into_iter()
-                    .collect();
+    Ok(place_alias_map)
+}
 
-            if let Some(alias_place_names) = find_alias_city_names(city_record) {
-                for alias_place_name in alias_place_names {
-                    let name_vec: Vec<&str> =
-                        alias_place_name.split(',').map(|s| s.trim()).collect();
-                    if city_record.name != name_vec[0] && city_record.state_name != name_vec[1] {
-                        list_city_location_keys(city_record, Some(name_vec[0]), Some(name_vec[1]))
-                            .into_iter()
-                            .for_each(|location_key| {
-                                location_keys_set.insert(location_key);
-                            });
-                    }
-                    if city_record.state_name != name_vec[1] {
-                        list_city_location_keys(city_record, None, Some(name_vec[1]))
-                            .into_iter()
-                            .for_each(|location_key| {
-                                location_keys_set.insert(location_key);
-                            });
-                    }
-                    if city_record.name != name_vec[0] {
-                        list_city_location_keys(city_record, Some(name_vec[0]), None)
-                            .into_iter()
-                            .for_each(|location_key| {
-                                location_keys_set.insert(location_key);
-                            });
-                    }
-                }
+/// Reads `ISO2|policy` lines into `partial_match_policies`, skipping blank
+/// lines and lines starting with `#`.
+fn load_partial_match_policies(
+    source: LocationSource,
+    partial_match_policies: &mut PartialMatchPolicies,
+) -> Result<(), LocationFinderError> {
+    let buf_reader = io::BufReader::new(source.reader);
+    let mut policy_count = 0;
+    for (line_index, line) in buf_reader.lines().enumerate() {
+        let location = SourceLocation {
+            source_name: source.name.clone(),
+            line: Some(line_index as u64 + 1),
+        };
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                return Err(LocationFinderError::IO {
+                    location,
+                    source: err,
+                })
             }
-
-            let state_record = get_state_by_id(city_record.state_id).unwrap();
-            if let Some(alias_place_names) = find_alias_state_names(state_record) {
-                for alias_place_name in alias_place_names {
-                    let name_vec: Vec<&str> =
-                        alias_place_name.split(',').map(|s| s.trim()).collect();
-                    if state_record.name != name_vec[0] {
-                        list_city_location_keys(city_record, None, Some(name_vec[0]))
-                            .into_iter()
-                            .for_each(|location_key| {
-                                location_keys_set.insert(location_key);
-                            });
-                    }
-                }
+        };
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let line_vec: Vec<&str> = line.split('|').map(|s| s.trim()).collect();
+        let policy = match line_vec[..] {
+            [country_code_iso2, policy_name] if !country_code_iso2.is_empty() => {
+                PartialMatchPolicy::from_name(policy_name).map(|policy| (country_code_iso2, policy))
             }
-
-            for location_key in location_keys_set {
-                city_name_map.insert(location_key, city_record.id());
+            _ => None,
+        };
+        match policy {
+            Some(("*", policy)) => {
+                partial_match_policies.set_default(policy);
+            }
+            Some((country_code_iso2, policy)) => {
+                partial_match_policies.set(country_code_iso2, policy);
             }
+            None => return Err(LocationFinderError::InvalidPartialMatchPolicy { location, line }),
+        }
+        policy_count += 1;
+    }
+    info!(
+        "Loaded {} partial match policies from {}",
+        policy_count, source.name
+    );
+    Ok(())
+}
 
-            city_name_map
-        })
+/// A named input for one of the dataset files. The name is only used for
+/// logging and error reporting.
+struct LocationSource<'a> {
+    name: String,
+    reader: Box<dyn Read + 'a>,
 }
 
-/**
-fn list_state_location_keys(state_record: &LocationState) -> Vec<String> {
-    let mut location_keys = Vec::new();
-    let state_name = normalize_location_str(state_record.name());
-    let country_name = normalize_location_str(&state_record.country_name);
-    location_keys.push(location_key(None, Some(&state_name), Some(&country_name)));
-    let country_record = get_country_by_id(state_record.country_id).unwrap();
-    let country_code_iso2 = normalize_location_str(&country_record.iso2);
-    location_keys.push(location_key(
-        None,
-        Some(&state_name),
-        Some(&country_code_iso2),
-    ));
-    let country_code_iso3 = normalize_location_str(&country_record.iso3);
-    location_keys.push(location_key(
-        None,
-        Some(&state_name),
-        Some(&country_code_iso3),
-    ));
-    location_keys
-}
-
-static STATE_NAME_MAP: OnceLock<MultiMap<String, u64>> = OnceLock::new();
-fn init_state_name_map() -> MultiMap<String, u64> {
-    let state_id_map = STATE_ID_MAP.get_or_init(init_state_id_map);
-    state_id_map
-        .values()
-        .fold(MultiMap::new(), |mut state_name_map, state| {
-            for location_key in list_state_location_keys(state) {
-                state_name_map.insert(location_key, state.id());
-            }
-            state_name_map
+impl<'a> LocationSource<'a> {
+    fn open(path: &Path) -> Result<LocationSource<'a>, LocationFinderError> {
+        let name = path.display().to_string();
+        let file = File::open(path).map_err(|err| LocationFinderError::IO {
+            location: SourceLocation {
+                source_name: name.clone(),
+                line: None,
+            },
+            source: err,
+        })?;
+        Ok(LocationSource {
+            name,
+            reader: Box::new(file),
         })
+    }
 }
 
-fn list_country_location_keys(country_record: &LocationCountry) -> Vec<String> {
-    let mut location_keys = Vec::new();
-    let country_name = normalize_location_str(country_record.name());
-    location_keys.push(location_key(None, None, Some(&country_name)));
-    let country_code_iso2 = normalize_location_str(&country_record.iso2);
-    location_keys.push(location_key(None, None, Some(&country_code_iso2)));
-    let country_code_iso3 = normalize_location_str(&country_record.iso3);
-    location_keys.push(location_key(None, None, Some(&country_code_iso3)));
-    location_keys
-}
-static COUNTRY_NAME_MAP: OnceLock<MultiMap<String, u64>> = OnceLock::new();
-fn init_country_name_map() -> MultiMap<String, u64> {
-    let country_id_map = COUNTRY_ID_MAP.get_or_init(init_country_id_map);
-    country_id_map
-        .values()
-        .fold(MultiMap::new(), |mut country_name_map, country| {
-            for location_key in list_country_location_keys(country) {
-                country_name_map.insert(location_key, country.id());
-            }
-            country_name_map
-        })
+/// Collects the dataset inputs for a [`LocationIndex`]. Each input can be any
+/// [`Read`] implementation, including `&[u8]` for embedded data, or a file
+/// path. The place alias list is optional; countries, states and cities are
+/// required.
+#[derive(Default)]
+pub struct LocationIndexBuilder<'a> {
+    countries: Option<LocationSource<'a>>,
+    states: Option<LocationSource<'a>>,
+    cities: Option<LocationSource<'a>>,
+    place_alias: Option<LocationSource<'a>>,
+    fuzzy_match_config: Option<FuzzyMatchConfig>,
+    abbreviation_table: Option<AbbreviationTable>,
+    token_set_match: bool,
+    partial_match_policies: Option<PartialMatchPolicies>,
+    partial_match_policy: Option<LocationSource<'a>>,
+    autocomplete: bool,
+}
+
+/// Settings for the optional fuzzy city name stage, which runs after the exact
+/// and partial stages found nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct FuzzyMatchConfig {
+    /// Largest Levenshtein distance between the normalized input city name and
+    /// a normalized city name that still counts as a match.
+    pub max_distance: usize,
+}
+
+impl Default for FuzzyMatchConfig {
+    fn default() -> Self {
+        FuzzyMatchConfig { max_distance: 2 }
+    }
+}
+
+impl<'a> LocationIndexBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn countries<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.countries = Some(LocationSource {
+            name: "countries.csv".to_string(),
+            reader: Box::new(reader),
+        });
+        self
+    }
+
+    pub fn states<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.states = Some(LocationSource {
+            name: "states.csv".to_string(),
+            reader: Box::new(reader),
+        });
+        self
+    }
+
+    pub fn cities<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.cities = Some(LocationSource {
+            name: "cities.csv".to_string(),
+            reader: Box::new(reader),
+        });
+        self
+    }
+
+    pub fn place_alias<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.place_alias = Some(LocationSource {
+            name: "place_alias.txt".to_string(),
+            reader: Box::new(reader),
+        });
+        self
+    }
+
+    /// Opens `countries.csv`, `states.csv` and `cities.csv` in
+    /// `location_dataset_dir`.
+    pub fn dataset_dir<P: AsRef<Path>>(
+        mut self,
+        location_dataset_dir: P,
+    ) -> Result<Self, LocationFinderError> {
+        let location_dataset_dir = location_dataset_dir.as_ref();
+        self.countries = Some(LocationSource::open(
+            &location_dataset_dir.join("countries.csv"),
+        )?);
+        self.states = Some(LocationSource::open(
+            &location_dataset_dir.join("states.csv"),
+        )?);
+        self.cities = Some(LocationSource::open(
+            &location_dataset_dir.join("cities.csv"),
+        )?);
+        Ok(self)
+    }
+
+    /// Enables the fuzzy city name stage and builds its index.
+    pub fn fuzzy_match(mut self, fuzzy_match_config: FuzzyMatchConfig) -> Self {
+        self.fuzzy_match_config = Some(fuzzy_match_config);
+        self
+    }
+
+    /// Replaces the built-in abbreviation table used to expand city names
+    /// such as "St. Louis" or "Ft Worth".
+    pub fn abbreviations(mut self, abbreviation_table: AbbreviationTable) -> Self {
+        self.abbreviation_table = Some(abbreviation_table);
+        self
+    }
+
+    /// Enables the token set stage, which matches city names regardless of
+    /// word order ("Hills Beverly" for "Beverly Hills") when the exact and
+    /// partial stages found nothing.
+    pub fn token_set_match(mut self, token_set_match: bool) -> Self {
+        self.token_set_match = token_set_match;
+        self
+    }
+
+    /// Enables [`LocationIndex::autocomplete`] and builds its prefix index.
+    pub fn autocomplete(mut self, autocomplete: bool) -> Self {
+        self.autocomplete = autocomplete;
+        self
+    }
+
+    pub fn place_alias_file<P: AsRef<Path>>(
+        mut self,
+        place_alias_filename: P,
+    ) -> Result<Self, LocationFinderError> {
+        self.place_alias = Some(LocationSource::open(place_alias_filename.as_ref())?);
+        Ok(self)
+    }
+
+    /// Replaces the built-in per-country partial-match policies.
+    pub fn partial_match_policies(mut self, partial_match_policies: PartialMatchPolicies) -> Self {
+        self.partial_match_policies = Some(partial_match_policies);
+        self
+    }
+
+    /// Reads partial-match policies with one `ISO2|policy` entry per line,
+    /// e.g. `AU|require_exact_state`; `*` as the country sets the policy for
+    /// countries without an entry. Entries are applied on top of the
+    /// built-in policies or those passed to
+    /// [`LocationIndexBuilder::partial_match_policies`].
+    pub fn partial_match_policy<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.partial_match_policy = Some(LocationSource {
+            name: "partial_match_policy.txt".to_string(),
+            reader: Box::new(reader),
+        });
+        self
+    }
+
+    pub fn partial_match_policy_file<P: AsRef<Path>>(
+        mut self,
+        partial_match_policy_filename: P,
+    ) -> Result<Self, LocationFinderError> {
+        self.partial_match_policy = Some(LocationSource::open(
+            partial_match_policy_filename.as_ref(),
+        )?);
+        Ok(self)
+    }
+
+    /// Loads every source and builds the index. Fails if a row cannot be
+    /// parsed or a record refers to a state or country that is not in the
+    /// dataset, listing every such row or reference.
+    pub fn build(self) -> Result<LocationIndex, LocationFinderError> {
+        let countries: LoadedRecords<LocationCountry> = load_records_by_id(
+            self.countries
+                .ok_or(LocationFinderError::MissingSource("countries"))?,
+        )?;
+        let states: LoadedRecords<LocationState> = load_records_by_id(
+            self.states
+                .ok_or(LocationFinderError::MissingSource("states"))?,
+        )?;
+        let cities: LoadedRecords<LocationCity> = load_records_by_id(
+            self.cities
+                .ok_or(LocationFinderError::MissingSource("cities"))?,
+        )?;
+        let dangling_references = find_dangling_references(&countries, &states, &cities);
+        if !dangling_references.is_empty() {
+            for dangling_reference in &dangling_references {
+                error!("{}", dangling_reference);
+            }
+            return Err(LocationFinderError::DanglingReferences(dangling_references));
+        }
+        let place_alias_map = match self.place_alias {
+            Some(place_alias) => load_place_alias_map(place_alias)?,
+            None => MultiMap::new(),
+        };
+        let mut partial_match_policies = self.partial_match_policies.unwrap_or_default();
+        if let Some(partial_match_policy) = self.partial_match_policy {
+            load_partial_match_policies(partial_match_policy, &mut partial_match_policies)?;
+        }
+
+        let country_state_ids = build_child_id_map(
+            states
+                .id_map
+                .values()
+                .map(|state_record| (state_record.country_id, state_record.id)),
+        );
+        let state_city_ids = build_child_id_map(
+            cities
+                .id_map
+                .values()
+                .map(|city_record| (city_record.state_id, city_record.id)),
+        );
+        let mut city_wikidata_map: HashMap<String, Vec<CityId>> = HashMap::new();
+        for city_record in cities.id_map.values() {
+            let wikidata_id = normalize_wikidata_id(&city_record.wiki_data_id);
+            if !wikidata_id.is_empty() {
+                city_wikidata_map
+                    .entry(wikidata_id)
+                    .or_default()
+                    .push(city_record.id);
+            }
+        }
+        for city_ids in city_wikidata_map.values_mut() {
+            city_ids.sort_unstable();
+        }
+        let country_city_ids = build_child_id_map(
+            cities
+                .id_map
+                .values()
+                .map(|city_record| (city_record.country_id, city_record.id)),
+        );
+        let country_iso2_map = build_code_id_map(
+            countries
+                .id_map
+                .values()
+                .map(|country_record| (normalize_code(&country_record.iso2), country_record.id))
+                .filter(|(country_code, _)| !country_code.is_empty()),
+        );
+        let country_iso3_map = build_code_id_map(
+            countries
+                .id_map
+                .values()
+                .map(|country_record| (normalize_code(&country_record.iso3), country_record.id))
+                .filter(|(country_code, _)| !country_code.is_empty()),
+        );
+        let country_numeric_map = build_code_id_map(
+            countries
+                .id_map
+                .values()
+                .map(|country_record| (country_record.numeric_code, country_record.id)),
+        );
+        let state_code_map = build_code_id_map(
+            states
+                .id_map
+                .values()
+                .map(|state_record| {
+                    (
+                        (
+                            state_record.country_id,
+                            normalize_code(&state_record.state_code),
+                        ),
+                        state_record.id,
+                    )
+                })
+                .filter(|((_, state_code), _)| !state_code.is_empty()),
+        );
+
+        let mut location_index = LocationIndex {
+            city_id_map: cities.id_map,
+            state_id_map: states.id_map,
+            country_id_map: countries.id_map,
+            country_state_ids,
+            state_city_ids,
+            country_city_ids,
+            city_wikidata_map,
+            country_iso2_map,
+            country_iso3_map,
+            country_numeric_map,
+            state_code_map,
+            place_alias_map,
+            name_interner: NameInterner::default(),
+            city_name_map: MultiMap::new(),
+            city_alias_keys: HashMap::new(),
+            state_name_map: MultiMap::new(),
+            country_name_map: MultiMap::new(),
+            fuzzy_match_config: self.fuzzy_match_config,
+            city_fuzzy_index: HashMap::new(),
+            city_spatial_index: SpatialIndex::default(),
+            abbreviation_table: self.abbreviation_table.unwrap_or_default(),
+            token_set_match: self.token_set_match,
+            city_token_set_map: HashMap::new(),
+            partial_match_policies,
+            place_prefix_index: HashMap::new(),
+        };
+        let mut name_interner = NameInterner::default();
+        (location_index.city_name_map, location_index.city_alias_keys) =
+            location_index.build_city_name_map(&mut name_interner);
+        location_index.state_name_map = location_index.build_state_name_map(&mut name_interner);
+        location_index.country_name_map = location_index.build_country_name_map(&mut name_interner);
+        location_index.name_interner = name_interner;
+        sort_name_ids(&mut location_index.city_name_map);
+        sort_name_ids(&mut location_index.state_name_map);
+        sort_name_ids(&mut location_index.country_name_map);
+        location_index.city_spatial_index = SpatialIndex::new(
+            location_index
+                .city_id_map
+                .values()
+                .filter_map(|city_record| {
+                    Some((
+                        city_record.id,
+                        city_record.latitude?,
+                        city_record.longitude?,
+                    ))
+                }),
+        );
+        if location_index.fuzzy_match_config.is_some() {
+            location_index.city_fuzzy_index = location_index.build_city_fuzzy_index();
+        }
+        if location_index.token_set_match {
+            location_index.city_token_set_map = location_index.build_city_token_set_map();
+        }
+        if self.autocomplete {
+            location_index.place_prefix_index = location_index.build_place_prefix_index();
+        }
+        Ok(location_index)
+    }
 }
-*/
 
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub enum LocationMatchType {
     FullMatch {
-        city: u64,
-        state: u64,
-        country: u64,
+        city: CityId,
+        state: StateId,
+        country: CountryId,
+        provenance: MatchProvenance,
     },
     PartialMatch {
-        city: u64,
-        country: u64,
-        unmatched_state: u64,
+        city: CityId,
+        country: CountryId,
+        unmatched_state: StateId,
+    },
+    StateMatch {
+        state: StateId,
+        country: CountryId,
+    },
+    CountryMatch {
+        country: CountryId,
+    },
+    /// City and country matched, and the state only matched heuristically:
+    /// its name, code or an alias reached `similarity` (between the
+    /// threshold and 1) against the state input.
+    SimilarStateMatch {
+        city: CityId,
+        state: StateId,
+        country: CountryId,
+        similarity: f64,
+        provenance: MatchProvenance,
+    },
+    /// The city name matched once word order was ignored.
+    TokenSetMatch {
+        city: CityId,
+        state: StateId,
+        country: CountryId,
+    },
+    /// The city name only matched approximately, within `distance` edits.
+    FuzzyMatch {
+        city: CityId,
+        state: StateId,
+        country: CountryId,
+        distance: usize,
+    },
+    /// More than one record matched equally well. Candidates are sorted in
+    /// tie-break order, see [`LocationMatchType::resolve`].
+    Ambiguous {
+        candidates: Vec<LocationMatchType>,
     },
     NoMatch,
 }
 
-static PARTIAL_MATCH_COUNTRIES_TO_SKIP: OnceLock<HashSet<&'static str>> = OnceLock::new();
-fn init_partial_match_countries_to_skip() -> HashSet<&'static str> {
-    let mut countries_to_skip = HashSet::new();
-    countries_to_skip.insert("United States");
-    countries_to_skip
+impl LocationMatchType {
+    /// Collapses an ambiguous match into a single answer. The tie-break is
+    /// deterministic: the most specific match wins (full, then similar state,
+    /// then partial, then token set, then fuzzy, then state, then country)
+    /// and among equally specific matches the one with the lowest city, state
+    /// or country ID is chosen. Other match types are returned unchanged.
+    pub fn resolve(self) -> LocationMatchType {
+        match self {
+            LocationMatchType::Ambiguous { candidates } => candidates
+                .into_iter()
+                .min_by_key(LocationMatchType::tie_break_key)
+                .unwrap_or(LocationMatchType::NoMatch),
+            location_match => location_match,
+        }
+    }
+
+    pub(crate) fn tie_break_key(&self) -> (u8, u64) {
+        match self {
+            LocationMatchType::FullMatch { city, .. } => (0, city.0),
+            LocationMatchType::SimilarStateMatch { city, .. } => (1, city.0),
+            LocationMatchType::PartialMatch { city, .. } => (2, city.0),
+            LocationMatchType::TokenSetMatch { city, .. } => (3, city.0),
+            LocationMatchType::FuzzyMatch { city, .. } => (4, city.0),
+            LocationMatchType::StateMatch { state, .. } => (5, state.0),
+            LocationMatchType::CountryMatch { country } => (6, country.0),
+            LocationMatchType::Ambiguous { .. } => (7, 0),
+            LocationMatchType::NoMatch => (8, 0),
+        }
+    }
+
+    pub(crate) fn from_candidates(mut candidates: Vec<LocationMatchType>) -> LocationMatchType {
+        match candidates.len() {
+            0 => LocationMatchType::NoMatch,
+            1 => candidates.pop().unwrap(),
+            _ => {
+                candidates.sort_by_key(LocationMatchType::tie_break_key);
+                LocationMatchType::Ambiguous { candidates }
+            }
+        }
+    }
+}
+
+/// The rule that accepted a city match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum MatchOrigin {
+    /// The city, state and country all matched a generated key.
+    ExactKey,
+    /// City and country matched and no state was given.
+    StateMissing,
+    /// City and country matched, the state did not, and the country's
+    /// partial-match policy overrides that to a full match.
+    CountryOverride,
+    /// City and country matched and the state only matched heuristically.
+    StateSimilarity,
 }
 
-static PARTIAL_MATCH_COUNTRIES_TO_OVERRIDE: OnceLock<HashSet<&'static str>> = OnceLock::new();
-fn init_partial_match_countries_to_override() -> HashSet<&'static str> {
-    let mut countries_to_override = HashSet::new();
-    countries_to_override.insert("United Kingdom");
-    countries_to_override
+/// Which code the country input matched instead of a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum CountryCodeType {
+    Iso2,
+    Iso3,
 }
 
-pub fn find_location(
-    city_in: &str,
-    state_in: &str,
-    country_in: &str,
-) -> Result<LocationMatchType, LocationFinderError> {
-    let city = normalize_location_str(city_in);
-    let state = normalize_location_str(state_in);
-    let country = normalize_location_str(country_in);
-
-    let city_map_key = location_key(Some(&city), Some(&state), Some(&country));
-    let city_name_matches = CITY_NAME_MAP
-        .get_or_init(init_city_name_map)
-        .get_vec(&city_map_key);
-    if let Some(city_name_matches) = city_name_matches {
-        if let Some(city_id) = city_name_matches.iter().next() {
-            let city_record = get_city_by_id(*city_id).unwrap();
-            let state_record = get_state_by_id(city_record.state_id).unwrap();
-            let country_record = get_country_by_id(city_record.country_id).unwrap();
-            return Ok(LocationMatchType::FullMatch {
-                city: city_record.id,
-                state: state_record.id,
-                country: country_record.id,
-            });
+/// How a city match was produced, so exact matches can be told apart from
+/// heuristic ones.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct MatchProvenance {
+    pub origin: MatchOrigin,
+    /// The state input was the state code rather than its name.
+    pub state_code: bool,
+    /// The country input was an ISO code rather than a name.
+    pub country_code: Option<CountryCodeType>,
+    /// The place alias entry the matching key was generated from, if any.
+    pub place_alias: Option<PlaceAliasEntry>,
+}
+
+/// Why a candidate was produced and what contributed to its score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchReason {
+    /// The city, state and country all matched a generated key.
+    ExactKey,
+    /// The matching key was generated from a `place_alias.txt` entry.
+    PlaceAlias,
+    /// The state input matched the state code rather than the name.
+    StateCode,
+    /// The country input matched the ISO2 or ISO3 code.
+    IsoCountryCode,
+    /// City and country matched and no state was given.
+    StateMissing,
+    /// No country was given, so all countries were searched.
+    CountryMissing,
+    /// City and country matched and the country is configured to accept that
+    /// as a full match.
+    CountryOverride,
+    /// City and country matched and the state input was similar, but not
+    /// equal, to the state's name, code or an alias.
+    StateSimilarity,
+    /// City and country matched but the state did not.
+    StateMismatch,
+    /// Only the state and country matched.
+    StateOnly,
+    /// Only the country matched.
+    CountryOnly,
+    /// The city name matched once word order was ignored.
+    TokenSet,
+    /// The city name matched approximately, within this many edits.
+    FuzzyDistance(usize),
+}
+
+/// One possible interpretation of a query, scored between 0 and 1.
+#[derive(Debug, Clone)]
+pub struct LocationCandidate {
+    pub location: LocationMatchType,
+    pub score: f64,
+    pub reasons: Vec<MatchReason>,
+}
+
+/// The stage of the matching pipeline a candidate came from, in the order
+/// the stages are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CandidateStage {
+    FullKey,
+    StateMissing,
+    CountryOverride,
+    StateSimilarity,
+    StateMismatch,
+    TokenSet,
+    Fuzzy,
+    StateOnly,
+    CountryOnly,
+}
+
+impl CandidateStage {
+    /// Stages in the same group are equally good answers for
+    /// [`LocationIndex::find_location`]; the lowest group that produced any
+    /// candidate decides the match.
+    fn decision_group(self) -> u8 {
+        match self {
+            CandidateStage::FullKey => 0,
+            CandidateStage::StateMissing
+            | CandidateStage::CountryOverride
+            | CandidateStage::StateSimilarity => 1,
+            CandidateStage::StateMismatch => 2,
+            CandidateStage::TokenSet => 3,
+            CandidateStage::Fuzzy => 4,
+            CandidateStage::StateOnly => 5,
+            CandidateStage::CountryOnly => 6,
         }
     }
+}
 
-    let city_map_key = location_key(Some(&city), None, Some(&country));
-    let city_name_matches = CITY_NAME_MAP
-        .get_or_init(init_city_name_map)
-        .get_vec(&city_map_key);
-    let mut partial_matches: Vec<LocationMatchType> = vec![];
-    if let Some(city_name_matches) = city_name_matches {
-        for city_id in city_name_matches {
-            let city_record = get_city_by_id(*city_id).unwrap();
-            let country_record = get_country_by_id(city_record.country_id).unwrap();
-            if PARTIAL_MATCH_COUNTRIES_TO_SKIP
-                .get_or_init(init_partial_match_countries_to_skip)
-                .get(country_record.name())
-                .is_some()
-            {
-                continue;
-            }
-            if PARTIAL_MATCH_COUNTRIES_TO_OVERRIDE
-                .get_or_init(init_partial_match_countries_to_override)
-                .get(country_record.name())
-                .is_some()
-            {
-                return Ok(LocationMatchType::FullMatch {
-                    city: city_record.id,
-                    state: city_record.state_id,
-                    country: city_record.country_id,
-                });
-            }
-            let unmatched_state_record = get_state_by_id(city_record.state_id).unwrap();
-            let unmatched_state_name = normalize_location_str(unmatched_state_record.name());
-            if unmatched_state_name.contains(&state) || state.contains(&unmatched_state_name) {
-                debug!(
-                    "Partial name match: {} vs {}",
-                    state_in,
-                    unmatched_state_record.name()
-                );
-                return Ok(LocationMatchType::FullMatch {
-                    city: city_record.id,
-                    state: city_record.state_id,
-                    country: city_record.country_id,
-                });
-            }
-            partial_matches.push(LocationMatchType::PartialMatch {
-                city: city_record.id,
-                country: city_record.country_id,
-                unmatched_state: city_record.state_id,
-            });
+struct StagedCandidate {
+    stage: CandidateStage,
+    candidate: LocationCandidate,
+}
+
+fn staged_location_matches(
+    staged_candidates: &[StagedCandidate],
+) -> impl Iterator<Item = &LocationMatchType> {
+    staged_candidates
+        .iter()
+        .map(|staged_candidate| &staged_candidate.candidate.location)
+}
+
+/// What a city key lookup matched besides the city name.
+struct CityKeyMatch<'a> {
+    place_alias: Option<&'a PlaceAliasEntry>,
+    state_code: bool,
+    country_code: Option<CountryCodeType>,
+    country_missing: bool,
+}
+
+impl CityKeyMatch<'_> {
+    fn provenance(&self, origin: MatchOrigin) -> MatchProvenance {
+        MatchProvenance {
+            origin,
+            state_code: self.state_code,
+            country_code: self.country_code,
+            place_alias: self.place_alias.cloned(),
+        }
+    }
+
+    fn reasons(&self) -> Vec<MatchReason> {
+        let mut reasons = Vec::new();
+        if self.place_alias.is_some() {
+            reasons.push(MatchReason::PlaceAlias);
         }
-        if partial_matches.len() == 1 {
-            return Ok(partial_matches.into_iter().next().unwrap());
+        if self.state_code {
+            reasons.push(MatchReason::StateCode);
+        }
+        if self.country_code.is_some() {
+            reasons.push(MatchReason::IsoCountryCode);
+        }
+        if self.country_missing {
+            reasons.push(MatchReason::CountryMissing);
+        }
+        reasons
+    }
+}
+
+const SCORE_EXACT_KEY: f64 = 1.0;
+const SCORE_STATE_MISSING: f64 = 0.9;
+const SCORE_COUNTRY_OVERRIDE: f64 = 0.9;
+const SCORE_STATE_SIMILARITY: f64 = 0.8;
+const SCORE_STATE_MISMATCH: f64 = 0.6;
+const SCORE_TOKEN_SET: f64 = 0.5;
+const SCORE_FUZZY: f64 = 0.55;
+const SCORE_FUZZY_PER_EDIT_PENALTY: f64 = 0.1;
+const SCORE_STATE_ONLY: f64 = 0.5;
+const SCORE_COUNTRY_ONLY: f64 = 0.3;
+const SCORE_PLACE_ALIAS_PENALTY: f64 = 0.05;
+
+/// State inputs shorter than this, ignoring separators, never match a state
+/// heuristically.
+const MIN_STATE_SIMILARITY_LEN: usize = 3;
+/// The similarity a state must reach to match heuristically.
+const STATE_SIMILARITY_THRESHOLD: f64 = 0.75;
+
+/// Wikidata ID problems in the city records, see
+/// [`LocationIndex::wikidata_report`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WikidataReport {
+    pub shared_wikidata_ids: Vec<SharedWikidataId>,
+    pub missing_wikidata_ids: Vec<CityId>,
+}
+
+/// A Wikidata ID and the cities that share it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SharedWikidataId {
+    pub wikidata_id: String,
+    pub cities: Vec<CityId>,
+}
+
+/// A place suggested by [`LocationIndex::autocomplete`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PlaceSuggestion {
+    pub place: PlaceId,
+    pub name: String,
+    /// The state of a suggested city, or the suggested state itself.
+    pub state: Option<StateId>,
+    pub country: CountryId,
+}
+
+/// A place under one of its normalized names in the prefix index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlacePrefixEntry {
+    place: PlaceId,
+    /// The name is the record's own name rather than an alias or native
+    /// name.
+    own_name: bool,
+    /// Lower ranks first: capitals and countries, then states, then other
+    /// cities.
+    weight: u8,
+}
+
+const PLACE_WEIGHT_CAPITAL: u8 = 0;
+const PLACE_WEIGHT_STATE: u8 = 1;
+const PLACE_WEIGHT_OTHER: u8 = 2;
+
+/// An owned, immutable view of one version of the location dataset: the
+/// country/state/city records, the place alias table and the name index
+/// built from them.
+pub struct LocationIndex {
+    city_id_map: HashMap<CityId, LocationCity>,
+    state_id_map: HashMap<StateId, LocationState>,
+    country_id_map: HashMap<CountryId, LocationCountry>,
+    country_state_ids: HashMap<CountryId, Vec<StateId>>,
+    state_city_ids: HashMap<StateId, Vec<CityId>>,
+    country_city_ids: HashMap<CountryId, Vec<CityId>>,
+    city_wikidata_map: HashMap<String, Vec<CityId>>,
+    country_iso2_map: HashMap<String, CountryId>,
+    country_iso3_map: HashMap<String, CountryId>,
+    country_numeric_map: HashMap<u32, CountryId>,
+    state_code_map: HashMap<(CountryId, String), StateId>,
+    place_alias_map: MultiMap<String, PlaceAliasEntry>,
+    name_interner: NameInterner,
+    city_name_map: MultiMap<LocationKey, CityId>,
+    /// The place alias entry behind each city key that only exists because
+    /// of an alias.
+    city_alias_keys: HashMap<(LocationKey, CityId), PlaceAliasEntry>,
+    state_name_map: MultiMap<LocationKey, StateId>,
+    country_name_map: MultiMap<LocationKey, CountryId>,
+    fuzzy_match_config: Option<FuzzyMatchConfig>,
+    city_fuzzy_index: HashMap<CountryId, BkTree<CityId>>,
+    city_spatial_index: SpatialIndex,
+    abbreviation_table: AbbreviationTable,
+    token_set_match: bool,
+    city_token_set_map: HashMap<(CountryId, String), Vec<CityId>>,
+    partial_match_policies: PartialMatchPolicies,
+    place_prefix_index: HashMap<CountryId, PrefixTrie<PlacePrefixEntry>>,
+}
+
+impl LocationIndex {
+    pub fn builder<'a>() -> LocationIndexBuilder<'a> {
+        LocationIndexBuilder::new()
+    }
+
+    /// Loads `countries.csv`, `states.csv` and `cities.csv` from
+    /// `location_dataset_dir` along with the place alias file, and builds the
+    /// name index.
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(
+        location_dataset_dir: P,
+        place_alias_filename: Q,
+    ) -> Result<LocationIndex, LocationFinderError> {
+        LocationIndexBuilder::new()
+            .dataset_dir(location_dataset_dir)?
+            .place_alias_file(place_alias_filename)?
+            .build()
+    }
+
+    fn find_name_ids<'a, I>(
+        &self,
+        name_map: &'a MultiMap<LocationKey, I>,
+        location_key: &NormalizedKey,
+    ) -> Option<&'a Vec<I>> {
+        self.name_interner
+            .lookup_key(location_key)
+            .and_then(|location_key| name_map.get_vec(&location_key))
+    }
+
+    fn find_name_ids_traced<'a, I: Copy + Into<PlaceId>>(
+        &self,
+        name_map: &'a MultiMap<LocationKey, I>,
+        location_key: &NormalizedKey,
+        stage: MatchStage,
+        trace: &mut LocationTrace,
+    ) -> Option<&'a Vec<I>> {
+        let ids = self.find_name_ids(name_map, location_key);
+        trace.key_tried(stage, location_key, ids);
+        ids
+    }
+
+    pub fn get_city_by_id(&self, id: CityId) -> Option<&LocationCity> {
+        self.city_id_map.get(&id)
+    }
+
+    /// Looks up a city by its Wikidata ID, e.g. "Q1726". If several cities
+    /// share the ID, the one with the lowest ID is returned; see
+    /// [`LocationIndex::wikidata_report`].
+    pub fn get_city_by_wikidata(&self, wikidata_id: &str) -> Option<&LocationCity> {
+        self.city_wikidata_map
+            .get(&normalize_wikidata_id(wikidata_id))
+            .and_then(|city_ids| city_ids.first())
+            .and_then(|city_id| self.get_city_by_id(*city_id))
+    }
+
+    /// Lists the Wikidata IDs shared by more than one city and the cities
+    /// without one, both sorted.
+    pub fn wikidata_report(&self) -> WikidataReport {
+        let mut shared_wikidata_ids: Vec<SharedWikidataId> = self
+            .city_wikidata_map
+            .iter()
+            .filter(|(_, city_ids)| city_ids.len() > 1)
+            .map(|(wikidata_id, city_ids)| SharedWikidataId {
+                wikidata_id: wikidata_id.clone(),
+                cities: city_ids.clone(),
+            })
+            .collect();
+        shared_wikidata_ids.sort_by(|a, b| a.wikidata_id.cmp(&b.wikidata_id));
+        let mut missing_wikidata_ids: Vec<CityId> = self
+            .city_id_map
+            .values()
+            .filter(|city_record| normalize_wikidata_id(&city_record.wiki_data_id).is_empty())
+            .map(|city_record| city_record.id)
+            .collect();
+        missing_wikidata_ids.sort_unstable();
+        WikidataReport {
+            shared_wikidata_ids,
+            missing_wikidata_ids,
+        }
+    }
+
+    pub fn get_state_by_id(&self, id: StateId) -> Option<&LocationState> {
+        self.state_id_map.get(&id)
+    }
+
+    pub fn get_country_by_id(&self, id: CountryId) -> Option<&LocationCountry> {
+        self.country_id_map.get(&id)
+    }
+
+    /// Every country, in no particular order.
+    pub fn countries(&self) -> impl Iterator<Item = &LocationCountry> {
+        self.country_id_map.values()
+    }
+
+    /// Every state, in no particular order.
+    pub fn states(&self) -> impl Iterator<Item = &LocationState> {
+        self.state_id_map.values()
+    }
+
+    /// Every city, in no particular order.
+    pub fn cities(&self) -> impl Iterator<Item = &LocationCity> {
+        self.city_id_map.values()
+    }
+
+    /// The states of a country, in ID order. Unknown countries have none.
+    pub fn states_of(&self, country_id: CountryId) -> impl Iterator<Item = &LocationState> {
+        self.country_state_ids
+            .get(&country_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|state_id| self.get_state_by_id(*state_id))
+    }
+
+    /// The cities of a state, in ID order. Unknown states have none.
+    pub fn cities_of(&self, state_id: StateId) -> impl Iterator<Item = &LocationCity> {
+        self.state_city_ids
+            .get(&state_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|city_id| self.get_city_by_id(*city_id))
+    }
+
+    /// The cities of every state of a country, in ID order.
+    pub fn cities_of_country(&self, country_id: CountryId) -> impl Iterator<Item = &LocationCity> {
+        self.country_city_ids
+            .get(&country_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|city_id| self.get_city_by_id(*city_id))
+    }
+
+    pub fn parent_state(&self, city_id: CityId) -> Option<&LocationState> {
+        self.get_city_by_id(city_id)
+            .and_then(|city_record| self.get_state_by_id(city_record.state_id))
+    }
+
+    /// Returns the city closest to the given coordinates. Cities without
+    /// coordinates in the dataset are never returned, and nothing is returned
+    /// for a latitude outside ±90 degrees or a coordinate that is not finite.
+    pub fn nearest_city(&self, latitude: f64, longitude: f64) -> Option<CityDistance> {
+        self.city_spatial_index.nearest(latitude, longitude)
+    }
+
+    /// Returns every city within `radius_km` of the given coordinates,
+    /// closest first. Invalid coordinates, as for
+    /// [`LocationIndex::nearest_city`], find no cities.
+    pub fn cities_within(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> Vec<CityDistance> {
+        self.city_spatial_index
+            .within(latitude, longitude, radius_km)
+    }
+
+    fn find_alias_city_names(&self, city_record: &LocationCity) -> Option<&Vec<PlaceAliasEntry>> {
+        let alias_place_lookup_key = format!(
+            "{}, {}, {}",
+            city_record.name, city_record.state_name, city_record.country_name
+        );
+        self.place_alias_map
+            .get_vec(alias_place_lookup_key.as_str())
+    }
+
+    fn find_alias_state_names(
+        &self,
+        state_record: &LocationState,
+    ) -> Option<&Vec<PlaceAliasEntry>> {
+        let alias_place_lookup_key =
+            format!("{}, {}", state_record.name, state_record.country_name);
+        self.place_alias_map
+            .get_vec(alias_place_lookup_key.as_str())
+    }
+
+    fn list_city_location_keys(
+        &self,
+        city_record: &LocationCity,
+        city_alias: Option<&str>,
+        state_alias: Option<&str>,
+    ) -> Vec<NormalizedKey> {
+        let mut location_keys = Vec::new();
+        let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+        let city_name =
+            self.canonical_city_name(city_alias.unwrap_or(city_record.name()), country_record);
+        let state_name = normalize_location_str(state_alias.unwrap_or(&city_record.state_name));
+        let country_name = normalize_location_str(&city_record.country_name);
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_name),
+            Some(&country_name),
+        ));
+        location_keys.push(location_key(Some(&city_name), None, Some(&country_name)));
+        let state_record = self.get_state_by_id(city_record.state_id).unwrap();
+        let state_code = normalize_location_str(&state_record.state_code);
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_code),
+            Some(&country_name),
+        ));
+        let country_code_iso2 = normalize_location_str(&country_record.iso2);
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_name),
+            Some(&country_code_iso2),
+        ));
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_code),
+            Some(&country_code_iso2),
+        ));
+        location_keys.push(location_key(
+            Some(&city_name),
+            None,
+            Some(&country_code_iso2),
+        ));
+        let country_code_iso3 = normalize_location_str(&country_record.iso3);
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_name),
+            Some(&country_code_iso3),
+        ));
+        location_keys.push(location_key(
+            Some(&city_name),
+            Some(&state_code),
+            Some(&country_code_iso3),
+        ));
+        location_keys.push(location_key(
+            Some(&city_name),
+            None,
+            Some(&country_code_iso3),
+        ));
+        if let Some(country_native) = normalized_native_name(country_record) {
+            location_keys.push(location_key(
+                Some(&city_name),
+                Some(&state_name),
+                Some(&country_native),
+            ));
+            location_keys.push(location_key(Some(&city_name), None, Some(&country_native)));
+        }
+        // Keys without a country, for queries that search all countries.
+        location_keys.push(location_key(Some(&city_name), Some(&state_name), None));
+        location_keys.push(location_key(Some(&city_name), Some(&state_code), None));
+        location_keys.push(location_key(Some(&city_name), None, None));
+        location_keys
+    }
+
+    /// Keys generated from the place aliases of a city and of its state,
+    /// each with the alias entry they came from.
+    fn list_city_alias_location_keys(
+        &self,
+        city_record: &LocationCity,
+    ) -> Vec<(&PlaceAliasEntry, Vec<NormalizedKey>)> {
+        let mut alias_location_keys = Vec::new();
+        if let Some(alias_place_names) = self.find_alias_city_names(city_record) {
+            for alias_place_name in alias_place_names {
+                let name_vec: Vec<&str> =
+                    alias_place_name.name.split(',').map(|s| s.trim()).collect();
+                let mut location_keys = Vec::new();
+                if city_record.name != name_vec[0] && city_record.state_name != name_vec[1] {
+                    location_keys.extend(self.list_city_location_keys(
+                        city_record,
+                        Some(name_vec[0]),
+                        Some(name_vec[1]),
+                    ));
+                }
+                if city_record.state_name != name_vec[1] {
+                    location_keys.extend(self.list_city_location_keys(
+                        city_record,
+                        None,
+                        Some(name_vec[1]),
+                    ));
+                }
+                if city_record.name != name_vec[0] {
+                    location_keys.extend(self.list_city_location_keys(
+                        city_record,
+                        Some(name_vec[0]),
+                        None,
+                    ));
+                }
+                alias_location_keys.push((alias_place_name, location_keys));
+            }
+        }
+
+        let state_record = self.get_state_by_id(city_record.state_id).unwrap();
+        if let Some(alias_place_names) = self.find_alias_state_names(state_record) {
+            for alias_place_name in alias_place_names {
+                let name_vec: Vec<&str> =
+                    alias_place_name.name.split(',').map(|s| s.trim()).collect();
+                if state_record.name != name_vec[0] {
+                    alias_location_keys.push((
+                        alias_place_name,
+                        self.list_city_location_keys(city_record, None, Some(name_vec[0])),
+                    ));
+                }
+            }
+        }
+        alias_location_keys
+    }
+
+    /// The place alias entry that produced `location_key` for `city_id`, or
+    /// `None` if the key comes from the record's own names.
+    fn find_city_key_alias(
+        &self,
+        city_id: CityId,
+        location_key: &NormalizedKey,
+    ) -> Option<&PlaceAliasEntry> {
+        self.name_interner
+            .lookup_key(location_key)
+            .and_then(|location_key| self.city_alias_keys.get(&(location_key, city_id)))
+    }
+
+    /// Builds the city name index, along with the place alias entry behind
+    /// each key that the city's own names do not produce. When several
+    /// aliases produce the same key, the first one listed wins.
+    fn build_city_name_map(
+        &self,
+        name_interner: &mut NameInterner,
+    ) -> (
+        MultiMap<LocationKey, CityId>,
+        HashMap<(LocationKey, CityId), PlaceAliasEntry>,
+    ) {
+        let mut city_name_map = MultiMap::new();
+        let mut city_alias_keys = HashMap::new();
+        for city_record in self.city_id_map.values() {
+            let location_keys_set: HashSet<NormalizedKey> = self
+                .list_city_location_keys(city_record, None, None)
+                .into_iter()
+                .collect();
+            for location_key in &location_keys_set {
+                city_name_map.insert(name_interner.intern_key(location_key), city_record.id());
+            }
+            for (alias_place_name, location_keys) in self.list_city_alias_location_keys(city_record)
+            {
+                for location_key in location_keys {
+                    if location_keys_set.contains(&location_key) {
+                        continue;
+                    }
+                    let location_key = name_interner.intern_key(&location_key);
+                    if let Entry::Vacant(entry) =
+                        city_alias_keys.entry((location_key, city_record.id()))
+                    {
+                        entry.insert(alias_place_name.clone());
+                        city_name_map.insert(location_key, city_record.id());
+                    }
+                }
+            }
+        }
+        (city_name_map, city_alias_keys)
+    }
+
+    fn list_state_location_keys(
+        &self,
+        state_record: &LocationState,
+        state_alias: Option<&str>,
+    ) -> Vec<NormalizedKey> {
+        let mut location_keys = Vec::new();
+        let state_name = normalize_location_str(state_alias.unwrap_or(state_record.name()));
+        let state_code = normalize_location_str(&state_record.state_code);
+        let country_name = normalize_location_str(&state_record.country_name);
+        location_keys.push(location_key(None, Some(&state_name), Some(&country_name)));
+        location_keys.push(location_key(None, Some(&state_code), Some(&country_name)));
+        let country_record = self.get_country_by_id(state_record.country_id).unwrap();
+        let country_code_iso2 = normalize_location_str(&country_record.iso2);
+        location_keys.push(location_key(
+            None,
+            Some(&state_name),
+            Some(&country_code_iso2),
+        ));
+        location_keys.push(location_key(
+            None,
+            Some(&state_code),
+            Some(&country_code_iso2),
+        ));
+        let country_code_iso3 = normalize_location_str(&country_record.iso3);
+        location_keys.push(location_key(
+            None,
+            Some(&state_name),
+            Some(&country_code_iso3),
+        ));
+        location_keys.push(location_key(
+            None,
+            Some(&state_code),
+            Some(&country_code_iso3),
+        ));
+        if let Some(country_native) = normalized_native_name(country_record) {
+            location_keys.push(location_key(None, Some(&state_name), Some(&country_native)));
+        }
+        // State codes are too short to be meaningful without a country, so
+        // only the name is searchable across all countries.
+        location_keys.push(location_key(None, Some(&state_name), None));
+        location_keys
+    }
+
+    fn build_state_name_map(
+        &self,
+        name_interner: &mut NameInterner,
+    ) -> MultiMap<LocationKey, StateId> {
+        self.state_id_map
+            .values()
+            .fold(MultiMap::new(), |mut state_name_map, state_record| {
+                let mut location_keys_set: HashSet<NormalizedKey> = self
+                    .list_state_location_keys(state_record, None)
+                    .into_iter()
+                    .collect();
+
+                if let Some(alias_place_names) = self.find_alias_state_names(state_record) {
+                    for alias_place_name in alias_place_names {
+                        let name_vec: Vec<&str> =
+                            alias_place_name.name.split(',').map(|s| s.trim()).collect();
+                        if state_record.name != name_vec[0] {
+                            self.list_state_location_keys(state_record, Some(name_vec[0]))
+                                .into_iter()
+                                .for_each(|location_key| {
+                                    location_keys_set.insert(location_key);
+                                });
+                        }
+                    }
+                }
+
+                for location_key in location_keys_set {
+                    state_name_map
+                        .insert(name_interner.intern_key(&location_key), state_record.id());
+                }
+                state_name_map
+            })
+    }
+
+    fn list_country_location_keys(&self, country_record: &LocationCountry) -> Vec<NormalizedKey> {
+        let mut location_keys = Vec::new();
+        let country_name = normalize_location_str(country_record.name());
+        location_keys.push(location_key(None, None, Some(&country_name)));
+        let country_code_iso2 = normalize_location_str(&country_record.iso2);
+        location_keys.push(location_key(None, None, Some(&country_code_iso2)));
+        let country_code_iso3 = normalize_location_str(&country_record.iso3);
+        location_keys.push(location_key(None, None, Some(&country_code_iso3)));
+        if let Some(country_native) = normalized_native_name(country_record) {
+            location_keys.push(location_key(None, None, Some(&country_native)));
+        }
+        location_keys
+    }
+
+    fn build_country_name_map(
+        &self,
+        name_interner: &mut NameInterner,
+    ) -> MultiMap<LocationKey, CountryId> {
+        self.country_id_map.values().fold(
+            MultiMap::new(),
+            |mut country_name_map, country_record| {
+                let location_keys_set: HashSet<NormalizedKey> = self
+                    .list_country_location_keys(country_record)
+                    .into_iter()
+                    .collect();
+                for location_key in location_keys_set {
+                    country_name_map
+                        .insert(name_interner.intern_key(&location_key), country_record.id());
+                }
+                country_name_map
+            },
+        )
+    }
+
+    /// Builds one BK-tree of normalized city names (including place aliases)
+    /// per country, so fuzzy lookups only search the matched country.
+    fn build_city_fuzzy_index(&self) -> HashMap<CountryId, BkTree<CityId>> {
+        let mut city_fuzzy_index: HashMap<CountryId, BkTree<CityId>> = HashMap::new();
+        let mut city_records: Vec<&LocationCity> = self.city_id_map.values().collect();
+        city_records.sort_by_key(|city_record| city_record.id);
+        for city_record in city_records {
+            let country_tree = city_fuzzy_index.entry(city_record.country_id).or_default();
+            for city_name in self.list_canonical_city_names(city_record) {
+                country_tree.insert(&city_name, city_record.id);
+            }
+        }
+        city_fuzzy_index
+    }
+
+    /// Builds a map from country and sorted city name words to city IDs, for
+    /// matching city names regardless of word order.
+    fn build_city_token_set_map(&self) -> HashMap<(CountryId, String), Vec<CityId>> {
+        let mut city_token_set_map: HashMap<(CountryId, String), Vec<CityId>> = HashMap::new();
+        for city_record in self.city_id_map.values() {
+            for city_name in self.list_canonical_city_names(city_record) {
+                let city_ids = city_token_set_map
+                    .entry((city_record.country_id, sorted_words(&city_name)))
+                    .or_default();
+                if !city_ids.contains(&city_record.id) {
+                    city_ids.push(city_record.id);
+                }
+            }
+        }
+        for city_ids in city_token_set_map.values_mut() {
+            city_ids.sort_unstable();
+        }
+        city_token_set_map
+    }
+
+    /// Builds one trie of normalized country, state and city names (including
+    /// native names and place aliases) per country, so filtered lookups only
+    /// search the requested country.
+    fn build_place_prefix_index(&self) -> HashMap<CountryId, PrefixTrie<PlacePrefixEntry>> {
+        let mut place_prefix_index: HashMap<CountryId, PrefixTrie<PlacePrefixEntry>> =
+            HashMap::new();
+        for country_record in self.country_id_map.values() {
+            let country_trie = place_prefix_index.entry(country_record.id).or_default();
+            let mut entry = PlacePrefixEntry {
+                place: PlaceId::Country(country_record.id),
+                own_name: true,
+                weight: PLACE_WEIGHT_CAPITAL,
+            };
+            country_trie.insert(&normalize_location_str(country_record.name()), entry);
+            if let Some(country_native) = normalized_native_name(country_record) {
+                entry.own_name = false;
+                country_trie.insert(&country_native, entry);
+            }
+        }
+        for state_record in self.state_id_map.values() {
+            let country_trie = place_prefix_index
+                .entry(state_record.country_id)
+                .or_default();
+            let mut entry = PlacePrefixEntry {
+                place: PlaceId::State(state_record.id),
+                own_name: true,
+                weight: PLACE_WEIGHT_STATE,
+            };
+            country_trie.insert(&normalize_location_str(state_record.name()), entry);
+            entry.own_name = false;
+            for alias_place_name in self
+                .find_alias_state_names(state_record)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(alias_state_name) = alias_place_name.name.split(',').next() {
+                    country_trie.insert(&normalize_location_str(alias_state_name), entry);
+                }
+            }
+        }
+        for city_record in self.city_id_map.values() {
+            let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+            let is_capital = normalize_location_str(city_record.name())
+                == normalize_location_str(&country_record.capital);
+            let country_trie = place_prefix_index
+                .entry(city_record.country_id)
+                .or_default();
+            let mut entry = PlacePrefixEntry {
+                place: PlaceId::City(city_record.id),
+                own_name: true,
+                weight: if is_capital {
+                    PLACE_WEIGHT_CAPITAL
+                } else {
+                    PLACE_WEIGHT_OTHER
+                },
+            };
+            for city_name in self.list_canonical_city_names(city_record) {
+                country_trie.insert(&city_name, entry);
+                entry.own_name = false;
+            }
+        }
+        place_prefix_index
+    }
+
+    /// Normalizes a city name and expands abbreviations using the table for
+    /// the city's country.
+    fn canonical_city_name(&self, city_name: &str, country_record: &LocationCountry) -> String {
+        self.abbreviation_table.expand(
+            &normalize_location_str(city_name),
+            Some(&country_record.iso2),
+        )
+    }
+
+    /// The canonical city name plus the canonical names of its place aliases.
+    fn list_canonical_city_names(&self, city_record: &LocationCity) -> Vec<String> {
+        let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+        let mut city_names = vec![self.canonical_city_name(city_record.name(), country_record)];
+        if let Some(alias_place_names) = self.find_alias_city_names(city_record) {
+            for alias_place_name in alias_place_names {
+                if let Some(alias_city_name) = alias_place_name.name.split(',').next() {
+                    city_names.push(self.canonical_city_name(alias_city_name, country_record));
+                }
+            }
+        }
+        city_names
+    }
+
+    /// How similar a normalized state input is to a state's name, code or
+    /// place aliases, from 0 to 1. Inputs shorter than
+    /// `MIN_STATE_SIMILARITY_LEN` letters always score 0.
+    fn state_similarity(&self, state: &str, state_record: &LocationState) -> f64 {
+        if state.chars().filter(|c| *c != '_').count() < MIN_STATE_SIMILARITY_LEN {
+            return 0.0;
+        }
+        let mut state_names = vec![
+            normalize_location_str(state_record.name()),
+            normalize_location_str(&state_record.state_code),
+        ];
+        if let Some(alias_place_names) = self.find_alias_state_names(state_record) {
+            for alias_place_name in alias_place_names {
+                if let Some(alias_state_name) = alias_place_name.name.split(',').next() {
+                    state_names.push(normalize_location_str(alias_state_name));
+                }
+            }
+        }
+        state_names
+            .iter()
+            .filter(|state_name| !state_name.is_empty())
+            .map(|state_name| name_similarity(state, state_name))
+            .fold(0.0, f64::max)
+    }
+
+    /// The country a normalized country input refers to, if exactly one
+    /// country matches it.
+    fn find_unique_country(&self, country: &str) -> Option<&LocationCountry> {
+        let country_map_key = location_key(None, None, Some(country));
+        match self.find_name_ids(&self.country_name_map, &country_map_key) {
+            Some(country_ids) if country_ids.len() == 1 => self.get_country_by_id(country_ids[0]),
+            _ => None,
+        }
+    }
+
+    /// Normalizes the state and country inputs. A numeric country input
+    /// ("276") is replaced by the country's ISO2 code, and an ISO 3166-2
+    /// state input ("DE-BY") by the state code, filling in the country when
+    /// none was given. A subdivision code outside the given country is left
+    /// as it is and will not match.
+    fn normalize_query_state_country(
+        &self,
+        state_in: &str,
+        country_in: &str,
+        trace: &mut LocationTrace,
+    ) -> (String, String) {
+        let numeric_country = parse_numeric_code(country_in).and_then(|numeric_code| {
+            self.find_country_by_numeric(numeric_code)
+                .map(|country_record| (numeric_code, country_record))
+        });
+        let mut country = match numeric_country {
+            Some((numeric_code, country_record)) => {
+                trace.rule_fired(|| MatchRule::NumericCountryCode {
+                    code: numeric_code,
+                    country: country_record.id,
+                });
+                normalize_location_str(&country_record.iso2)
+            }
+            None => normalize_location_str(country_in),
+        };
+        let subdivision_state = self
+            .find_state_by_iso3166_2(state_in)
+            .filter(|state_record| {
+                country.is_empty()
+                    || self
+                        .find_name_ids(
+                            &self.country_name_map,
+                            &location_key(None, None, Some(&country)),
+                        )
+                        .is_some_and(|country_ids| country_ids.contains(&state_record.country_id))
+            });
+        let state = match subdivision_state {
+            Some(state_record) => {
+                trace.rule_fired(|| MatchRule::SubdivisionCode {
+                    code: state_in.trim().to_string(),
+                    state: state_record.id,
+                });
+                if country.is_empty() {
+                    let country_record = self.get_country_by_id(state_record.country_id).unwrap();
+                    country = normalize_location_str(&country_record.iso2);
+                }
+                normalize_location_str(&state_record.state_code)
+            }
+            None => normalize_location_str(state_in),
+        };
+        (state, country)
+    }
+
+    /// Normalizes a query city name and expands abbreviations, using the
+    /// country's table when the country input is unambiguous.
+    fn normalize_query_city(&self, city_in: &str, country: &str) -> String {
+        let city = normalize_location_str(city_in);
+        let country_code_iso2 = self
+            .find_unique_country(country)
+            .map(|country_record| country_record.iso2.as_str());
+        self.abbreviation_table.expand(&city, country_code_iso2)
+    }
+
+    /// Returns cities whose name has the same words as `city` in any order,
+    /// or nothing when the stage is disabled or the country is not
+    /// unambiguous. State handling follows [`LocationIndex::select_city_matches`].
+    fn find_token_set_city_matches(&self, city: &str, state: &str, country: &str) -> Vec<CityId> {
+        if !self.token_set_match {
+            return vec![];
+        }
+        let Some(country_record) = self.find_unique_country(country) else {
+            return vec![];
+        };
+        let Some(city_ids) = self
+            .city_token_set_map
+            .get(&(country_record.id, sorted_words(city)))
+        else {
+            return vec![];
+        };
+        self.select_city_matches(
+            city_ids.iter().map(|city_id| (*city_id, 0)),
+            state,
+            country_record,
+        )
+        .into_iter()
+        .map(|(city_id, _)| city_id)
+        .collect()
+    }
+
+    /// Narrows `(city_id, distance)` pairs from an approximate stage down to
+    /// the closest ones. Cities whose state matches the input are preferred;
+    /// in countries that skip partial matches a given state must match.
+    fn select_city_matches(
+        &self,
+        city_matches: impl IntoIterator<Item = (CityId, usize)>,
+        state: &str,
+        country_record: &LocationCountry,
+    ) -> Vec<(CityId, usize)> {
+        let skip_partial_matches =
+            self.partial_match_policies.get(&country_record.iso2) == PartialMatchPolicy::Skip;
+
+        let mut best_matches: HashMap<CityId, (usize, bool)> = HashMap::new();
+        for (city_id, distance) in city_matches {
+            let city_record = self.get_city_by_id(city_id).unwrap();
+            let state_record = self.get_state_by_id(city_record.state_id).unwrap();
+            let state_matched = !state.is_empty()
+                && (state == normalize_location_str(state_record.name())
+                    || state == normalize_location_str(&state_record.state_code));
+            if skip_partial_matches && !state.is_empty() && !state_matched {
+                continue;
+            }
+            let best_match = best_matches
+                .entry(city_id)
+                .or_insert((distance, state_matched));
+            if distance < best_match.0 {
+                *best_match = (distance, state_matched);
+            }
+        }
+
+        let any_state_matched = best_matches
+            .values()
+            .any(|(_, state_matched)| *state_matched);
+        let Some(min_distance) = best_matches
+            .values()
+            .filter(|(_, state_matched)| *state_matched || !any_state_matched)
+            .map(|(distance, _)| *distance)
+            .min()
+        else {
+            return vec![];
+        };
+        let mut city_matches: Vec<(CityId, usize)> = best_matches
+            .into_iter()
+            .filter(|(_, (distance, state_matched))| {
+                *distance == min_distance && (*state_matched || !any_state_matched)
+            })
+            .map(|(city_id, (distance, _))| (city_id, distance))
+            .collect();
+        city_matches.sort_unstable();
+        city_matches
+    }
+
+    /// Returns the closest fuzzy city matches as `(city_id, distance)` pairs,
+    /// or nothing when fuzzy matching is disabled or the country is not
+    /// unambiguous. State handling follows [`LocationIndex::select_city_matches`].
+    fn find_fuzzy_city_matches(
+        &self,
+        city: &str,
+        state: &str,
+        country: &str,
+    ) -> Vec<(CityId, usize)> {
+        let Some(fuzzy_match_config) = self.fuzzy_match_config else {
+            return vec![];
+        };
+        let Some(country_record) = self.find_unique_country(country) else {
+            return vec![];
+        };
+        let Some(country_tree) = self.city_fuzzy_index.get(&country_record.id) else {
+            return vec![];
+        };
+        let fuzzy_matches = country_tree
+            .find(city, fuzzy_match_config.max_distance)
+            .into_iter()
+            .flat_map(|(distance, _, city_ids)| {
+                city_ids.iter().map(move |city_id| (*city_id, distance))
+            });
+        self.select_city_matches(fuzzy_matches, state, country_record)
+    }
+
+    /// Suggests up to `limit` places whose name starts with `prefix`, e.g.
+    /// "San Fr" for "San Francisco", optionally only within `country_in`.
+    /// Cities whose own name has the prefix come first, then states and
+    /// countries, then places that only matched through an alias or native
+    /// name. Within each group capitals rank before other cities, countries
+    /// before states, and the rest is alphabetical. The dataset does not
+    /// mark state capitals, so they rank like other cities. Returns nothing
+    /// unless [`LocationIndexBuilder::autocomplete`] was enabled.
+    pub fn autocomplete(
+        &self,
+        prefix: &str,
+        country_in: Option<&str>,
+        limit: usize,
+    ) -> Vec<PlaceSuggestion> {
+        let prefix = normalize_location_str(prefix);
+        if prefix.is_empty() {
+            return vec![];
+        }
+        let country_records: Vec<&LocationCountry> = match country_in {
+            Some(country_in) => self.find_country(country_in).into_iter().collect(),
+            None => self.country_id_map.values().collect(),
+        };
+        let mut best_entries: HashMap<PlaceId, PlacePrefixEntry> = HashMap::new();
+        for country_record in country_records {
+            let Some(country_trie) = self.place_prefix_index.get(&country_record.id) else {
+                continue;
+            };
+            // City names are indexed with abbreviations expanded, so "St. L"
+            // has to be looked up as "Saint L" too.
+            let expanded_prefix = self
+                .abbreviation_table
+                .expand(&prefix, Some(&country_record.iso2));
+            let mut entries = country_trie.find_prefix(&prefix);
+            if expanded_prefix != prefix {
+                entries.extend(country_trie.find_prefix(&expanded_prefix));
+            }
+            for entry in entries {
+                let best_entry = best_entries.entry(entry.place).or_insert(*entry);
+                best_entry.own_name |= entry.own_name;
+            }
+        }
+
+        let mut suggestions: Vec<(PlacePrefixEntry, PlaceSuggestion)> = best_entries
+            .into_values()
+            .filter_map(|entry| Some((entry, self.place_suggestion(entry.place)?)))
+            .collect();
+        suggestions.sort_by_cached_key(|(entry, suggestion)| {
+            let group = match (entry.own_name, entry.place) {
+                (true, PlaceId::City(_)) => 0,
+                (true, _) => 1,
+                (false, _) => 2,
+            };
+            (
+                group,
+                entry.weight,
+                normalize_location_str(&suggestion.name),
+                entry.place,
+            )
+        });
+        suggestions
+            .into_iter()
+            .take(limit)
+            .map(|(_, suggestion)| suggestion)
+            .collect()
+    }
+
+    fn place_suggestion(&self, place: PlaceId) -> Option<PlaceSuggestion> {
+        match place {
+            PlaceId::City(city_id) => {
+                self.get_city_by_id(city_id)
+                    .map(|city_record| PlaceSuggestion {
+                        place,
+                        name: city_record.name.clone(),
+                        state: Some(city_record.state_id),
+                        country: city_record.country_id,
+                    })
+            }
+            PlaceId::State(state_id) => {
+                self.get_state_by_id(state_id)
+                    .map(|state_record| PlaceSuggestion {
+                        place,
+                        name: state_record.name.clone(),
+                        state: Some(state_record.id),
+                        country: state_record.country_id,
+                    })
+            }
+            PlaceId::Country(country_id) => {
+                self.get_country_by_id(country_id)
+                    .map(|country_record| PlaceSuggestion {
+                        place,
+                        name: country_record.name.clone(),
+                        state: None,
+                        country: country_record.id,
+                    })
+            }
+        }
+    }
+
+    /// Looks up a state by name or state code within a country, e.g.
+    /// "Bavaria, Germany" or "BY, DE". If several states match, the one with
+    /// the lowest ID is returned.
+    pub fn find_state(&self, state_in: &str, country_in: &str) -> Option<&LocationState> {
+        let state = normalize_location_str(state_in);
+        let country = normalize_location_str(country_in);
+        let state_map_key = location_key(None, Some(&state), Some(&country));
+        self.find_name_ids(&self.state_name_map, &state_map_key)
+            .and_then(|state_ids| state_ids.iter().next())
+            .and_then(|state_id| self.get_state_by_id(*state_id))
+    }
+
+    /// Looks up a country by name, ISO2 or ISO3 code. If several countries
+    /// match, the one with the lowest ID is returned.
+    pub fn find_country(&self, country_in: &str) -> Option<&LocationCountry> {
+        let country = normalize_location_str(country_in);
+        let country_map_key = location_key(None, None, Some(&country));
+        self.find_name_ids(&self.country_name_map, &country_map_key)
+            .and_then(|country_ids| country_ids.iter().next())
+            .and_then(|country_id| self.get_country_by_id(*country_id))
+    }
+
+    /// Looks up a state by its ISO 3166-2 code, the country's ISO2 code and
+    /// the state code joined by a hyphen, e.g. "US-CA" or "DE-BY". Case is
+    /// ignored.
+    pub fn find_state_by_iso3166_2(&self, code: &str) -> Option<&LocationState> {
+        let (country_code_iso2, state_code) = code.trim().split_once('-')?;
+        if state_code.is_empty() {
+            return None;
+        }
+        let country_record = self.find_country_by_iso2(country_code_iso2)?;
+        self.state_code_map
+            .get(&(country_record.id, normalize_code(state_code)))
+            .and_then(|state_id| self.get_state_by_id(*state_id))
+    }
+
+    /// Looks up a country by its ISO 3166-1 numeric code, e.g. 276 for
+    /// Germany.
+    pub fn find_country_by_numeric(&self, numeric_code: u32) -> Option<&LocationCountry> {
+        self.country_numeric_map
+            .get(&numeric_code)
+            .and_then(|country_id| self.get_country_by_id(*country_id))
+    }
+
+    /// Looks up a country by its ISO2 code only, ignoring case.
+    pub fn find_country_by_iso2(&self, country_code_iso2: &str) -> Option<&LocationCountry> {
+        self.country_iso2_map
+            .get(&normalize_code(country_code_iso2))
+            .and_then(|country_id| self.get_country_by_id(*country_id))
+    }
+
+    /// Looks up a country by its ISO3 code only, ignoring case.
+    pub fn find_country_by_iso3(&self, country_code_iso3: &str) -> Option<&LocationCountry> {
+        self.country_iso3_map
+            .get(&normalize_code(country_code_iso3))
+            .and_then(|country_id| self.get_country_by_id(*country_id))
+    }
+
+    /// Matches the most specific level the input allows: city first (exact,
+    /// partial, then token set and fuzzy when enabled), then state and finally
+    /// country alone. The match type tells the caller which level was matched.
+    /// An empty state is ignored and an empty country searches all countries;
+    /// see [`LocationQuery`] for passing missing fields and hints explicitly.
+    /// Besides names, the country can be an ISO2, ISO3 or numeric code and
+    /// the state a state code or an ISO 3166-2 code such as "US-CA".
+    /// When several records match equally well the result is
+    /// [`LocationMatchType::Ambiguous`]; call [`LocationMatchType::resolve`]
+    /// to pick one deterministically.
+    pub fn find_location(
+        &self,
+        city_in: &str,
+        state_in: &str,
+        country_in: &str,
+    ) -> Result<LocationMatchType, LocationFinderError> {
+        self.find_location_traced(city_in, state_in, country_in, &mut LocationTrace::default())
+    }
+
+    pub(crate) fn find_location_traced(
+        &self,
+        city_in: &str,
+        state_in: &str,
+        country_in: &str,
+        trace: &mut LocationTrace,
+    ) -> Result<LocationMatchType, LocationFinderError> {
+        let staged_candidates =
+            self.list_staged_candidates(city_in, state_in, country_in, true, trace);
+        let Some(decision_group) = staged_candidates
+            .iter()
+            .map(|staged_candidate| staged_candidate.stage.decision_group())
+            .min()
+        else {
+            return Ok(LocationMatchType::NoMatch);
+        };
+        let mut location_matches: Vec<LocationMatchType> = staged_candidates
+            .into_iter()
+            .filter(|staged_candidate| staged_candidate.stage.decision_group() == decision_group)
+            .map(|staged_candidate| staged_candidate.candidate.location)
+            .collect();
+        // Of the heuristic state matches only the most similar count.
+        let best_similarity = location_matches
+            .iter()
+            .filter_map(|location_match| match location_match {
+                LocationMatchType::SimilarStateMatch { similarity, .. } => Some(*similarity),
+                _ => None,
+            })
+            .max_by(f64::total_cmp);
+        location_matches.retain(|location_match| {
+            !matches!(location_match, LocationMatchType::SimilarStateMatch { similarity, .. }
+                if Some(*similarity) != best_similarity)
+        });
+        Ok(LocationMatchType::from_candidates(location_matches))
+    }
+
+    /// Returns up to `limit` candidates ordered by descending score. Scores
+    /// start from the stage that produced the candidate (exact key, country
+    /// override, state similarity, state mismatch, state only, country only),
+    /// lose a little when a place alias was needed, and are divided by the
+    /// number of candidates produced by the same or a better stage, so an
+    /// ambiguous match never scores as high as a unique one.
+    pub fn find_location_candidates(
+        &self,
+        city_in: &str,
+        state_in: &str,
+        country_in: &str,
+        limit: usize,
+    ) -> Vec<LocationCandidate> {
+        let mut candidates = self.list_location_candidates(city_in, state_in, country_in);
+        candidates.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.location.tie_break_key().cmp(&b.location.tie_break_key()))
+        });
+        candidates.truncate(limit);
+        candidates
+    }
+
+    /// The scored candidates from the first level that produced any,
+    /// unsorted.
+    pub(crate) fn list_location_candidates(
+        &self,
+        city_in: &str,
+        state_in: &str,
+        country_in: &str,
+    ) -> Vec<LocationCandidate> {
+        let staged_candidates = self.list_staged_candidates(
+            city_in,
+            state_in,
+            country_in,
+            false,
+            &mut LocationTrace::default(),
+        );
+        let mut candidates = Vec::new();
+        let mut stage_candidates = Vec::new();
+        let mut current_stage = None;
+        for staged_candidate in staged_candidates {
+            if current_stage != Some(staged_candidate.stage) {
+                push_stage_candidates(&mut candidates, std::mem::take(&mut stage_candidates));
+                current_stage = Some(staged_candidate.stage);
+            }
+            stage_candidates.push(staged_candidate.candidate);
+        }
+        push_stage_candidates(&mut candidates, stage_candidates);
+        candidates
+    }
+
+    /// The matcher behind both [`LocationIndex::find_location`] and
+    /// [`LocationIndex::find_location_candidates`]. Normalizes the input and
+    /// runs the stages in order until one level (city keys, token set, fuzzy,
+    /// state, country) produces candidates, each with its stage's base
+    /// score. With `stop_at_full_key`, full key matches end the search before
+    /// the city and country key is looked up.
+    fn list_staged_candidates(
+        &self,
+        city_in: &str,
+        state_in: &str,
+        country_in: &str,
+        stop_at_full_key: bool,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let (state, country) = self.normalize_query_state_country(state_in, country_in, trace);
+        let city = self.normalize_query_city(city_in, &country);
+        trace.normalized_input(&city, &state, &country);
+        trace.rule_fired(|| {
+            let unexpanded_city = normalize_location_str(city_in);
+            (unexpanded_city != city).then(|| MatchRule::AbbreviationExpanded {
+                from: unexpanded_city,
+                to: city.clone(),
+            })
+        });
+        if country.is_empty() {
+            trace.rule_fired(|| MatchRule::CountryMissing);
+        }
+        if !city.is_empty() {
+            let mut staged_candidates =
+                self.list_full_key_candidates(&city, &state, &country, trace);
+            if staged_candidates.is_empty() || !stop_at_full_key {
+                let full_match_city_ids: Vec<CityId> = staged_candidates
+                    .iter()
+                    .filter_map(
+                        |staged_candidate| match staged_candidate.candidate.location {
+                            LocationMatchType::FullMatch { city, .. } => Some(city),
+                            _ => None,
+                        },
+                    )
+                    .collect();
+                staged_candidates.extend(self.list_city_country_key_candidates(
+                    &city,
+                    &state,
+                    &country,
+                    &full_match_city_ids,
+                    trace,
+                ));
+            }
+            if !staged_candidates.is_empty() {
+                return staged_candidates;
+            }
+            let staged_candidates = self.list_token_set_candidates(&city, &state, &country, trace);
+            if !staged_candidates.is_empty() {
+                return staged_candidates;
+            }
+            let staged_candidates = self.list_fuzzy_candidates(&city, &state, &country, trace);
+            if !staged_candidates.is_empty() {
+                return staged_candidates;
+            }
+        }
+        if !state.is_empty() {
+            let staged_candidates = self.list_state_candidates(&state, &country, trace);
+            if !staged_candidates.is_empty() {
+                return staged_candidates;
+            }
+        }
+        self.list_country_candidates(&country, trace)
+    }
+
+    /// Cities matching the city, state and country key. Without a state the
+    /// full key is the city/country key, which the next stage handles.
+    fn list_full_key_candidates(
+        &self,
+        city: &str,
+        state: &str,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        if state.is_empty() {
+            return vec![];
+        }
+        let city_map_key = location_key(Some(city), Some(state), Some(country));
+        let staged_candidates: Vec<StagedCandidate> = self
+            .find_name_ids_traced(
+                &self.city_name_map,
+                &city_map_key,
+                MatchStage::FullKey,
+                trace,
+            )
+            .into_iter()
+            .flatten()
+            .map(|city_id| {
+                let city_record = self.get_city_by_id(*city_id).unwrap();
+                let key_match =
+                    self.city_key_match(city_record, &city_map_key, state, country, trace);
+                let mut reasons = vec![MatchReason::ExactKey];
+                reasons.extend(key_match.reasons());
+                StagedCandidate {
+                    stage: CandidateStage::FullKey,
+                    candidate: LocationCandidate {
+                        location: LocationMatchType::FullMatch {
+                            city: city_record.id,
+                            state: city_record.state_id,
+                            country: city_record.country_id,
+                            provenance: key_match.provenance(MatchOrigin::ExactKey),
+                        },
+                        score: SCORE_EXACT_KEY,
+                        reasons,
+                    },
+                }
+            })
+            .collect();
+        trace.stage_candidates(
+            MatchStage::FullKey,
+            staged_location_matches(&staged_candidates),
+        );
+        staged_candidates
+    }
+
+    /// Cities matching the city and country key whose state was missing or
+    /// did not match, judged by the country's partial-match policy. Cities in
+    /// `full_match_city_ids` were already matched by the full key.
+    fn list_city_country_key_candidates(
+        &self,
+        city: &str,
+        state: &str,
+        country: &str,
+        full_match_city_ids: &[CityId],
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let city_map_key = location_key(Some(city), None, Some(country));
+        let mut missing_state_candidates = Vec::new();
+        let mut override_candidates = Vec::new();
+        let mut similar_state_candidates = Vec::new();
+        let mut mismatch_candidates = Vec::new();
+        for city_id in self
+            .find_name_ids_traced(
+                &self.city_name_map,
+                &city_map_key,
+                MatchStage::CityCountryKey,
+                trace,
+            )
+            .into_iter()
+            .flatten()
+        {
+            if full_match_city_ids.contains(city_id) {
+                continue;
+            }
+            let city_record = self.get_city_by_id(*city_id).unwrap();
+            let key_match = self.city_key_match(city_record, &city_map_key, state, country, trace);
+            let mut reasons = key_match.reasons();
+            if state.is_empty() {
+                trace.rule_fired(|| MatchRule::StateMissing {
+                    city: city_record.id,
+                });
+                reasons.insert(0, MatchReason::StateMissing);
+                missing_state_candidates.push(LocationCandidate {
+                    location: LocationMatchType::FullMatch {
+                        city: city_record.id,
+                        state: city_record.state_id,
+                        country: city_record.country_id,
+                        provenance: key_match.provenance(MatchOrigin::StateMissing),
+                    },
+                    score: SCORE_STATE_MISSING,
+                    reasons,
+                });
+                continue;
+            }
+            let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+            let partial_match_policy = self.partial_match_policies.get(&country_record.iso2);
+            trace.rule_fired(|| MatchRule::PartialMatchPolicy {
+                city: city_record.id,
+                country_code: country_record.iso2.clone(),
+                policy: partial_match_policy,
+            });
+            match partial_match_policy {
+                PartialMatchPolicy::Skip => continue,
+                PartialMatchPolicy::Override => {
+                    reasons.insert(0, MatchReason::CountryOverride);
+                    override_candidates.push(LocationCandidate {
+                        location: LocationMatchType::FullMatch {
+                            city: city_record.id,
+                            state: city_record.state_id,
+                            country: city_record.country_id,
+                            provenance: key_match.provenance(MatchOrigin::CountryOverride),
+                        },
+                        score: SCORE_COUNTRY_OVERRIDE,
+                        reasons,
+                    });
+                    continue;
+                }
+                PartialMatchPolicy::StateSimilarity => {
+                    let unmatched_state_record =
+                        self.get_state_by_id(city_record.state_id).unwrap();
+                    let similarity = self.state_similarity(state, unmatched_state_record);
+                    let accepted = similarity >= STATE_SIMILARITY_THRESHOLD;
+                    trace.rule_fired(|| MatchRule::StateSimilarity {
+                        city: city_record.id,
+                        state: unmatched_state_record.id,
+                        similarity,
+                        accepted,
+                    });
+                    if accepted {
+                        debug!(
+                            "Similar state name: {} vs {} ({:.2})",
+                            state,
+                            unmatched_state_record.name(),
+                            similarity
+                        );
+                        reasons.insert(0, MatchReason::StateSimilarity);
+                        similar_state_candidates.push(LocationCandidate {
+                            location: LocationMatchType::SimilarStateMatch {
+                                city: city_record.id,
+                                state: city_record.state_id,
+                                country: city_record.country_id,
+                                similarity,
+                                provenance: key_match.provenance(MatchOrigin::StateSimilarity),
+                            },
+                            score: SCORE_STATE_SIMILARITY * similarity,
+                            reasons,
+                        });
+                        continue;
+                    }
+                }
+                PartialMatchPolicy::RequireExactState => {}
+            }
+            reasons.insert(0, MatchReason::StateMismatch);
+            mismatch_candidates.push(LocationCandidate {
+                location: LocationMatchType::PartialMatch {
+                    city: city_record.id,
+                    country: city_record.country_id,
+                    unmatched_state: city_record.state_id,
+                },
+                score: SCORE_STATE_MISMATCH,
+                reasons,
+            });
+        }
+        let staged_candidates: Vec<StagedCandidate> = [
+            (CandidateStage::StateMissing, missing_state_candidates),
+            (CandidateStage::CountryOverride, override_candidates),
+            (CandidateStage::StateSimilarity, similar_state_candidates),
+            (CandidateStage::StateMismatch, mismatch_candidates),
+        ]
+        .into_iter()
+        .flat_map(|(stage, candidates)| {
+            candidates
+                .into_iter()
+                .map(move |candidate| StagedCandidate { stage, candidate })
+        })
+        .collect();
+        trace.stage_candidates(
+            MatchStage::CityCountryKey,
+            staged_location_matches(&staged_candidates),
+        );
+        staged_candidates
+    }
+
+    fn list_token_set_candidates(
+        &self,
+        city: &str,
+        state: &str,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let staged_candidates: Vec<StagedCandidate> = self
+            .find_token_set_city_matches(city, state, country)
+            .into_iter()
+            .map(|city_id| {
+                let city_record = self.get_city_by_id(city_id).unwrap();
+                let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+                let mut reasons = vec![MatchReason::TokenSet];
+                if is_iso_country_code(country_record, country) {
+                    reasons.push(MatchReason::IsoCountryCode);
+                }
+                StagedCandidate {
+                    stage: CandidateStage::TokenSet,
+                    candidate: LocationCandidate {
+                        location: LocationMatchType::TokenSetMatch {
+                            city: city_record.id,
+                            state: city_record.state_id,
+                            country: city_record.country_id,
+                        },
+                        score: SCORE_TOKEN_SET,
+                        reasons,
+                    },
+                }
+            })
+            .collect();
+        if self.token_set_match {
+            trace.stage_candidates(
+                MatchStage::TokenSet,
+                staged_location_matches(&staged_candidates),
+            );
+        }
+        staged_candidates
+    }
+
+    fn list_fuzzy_candidates(
+        &self,
+        city: &str,
+        state: &str,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let staged_candidates: Vec<StagedCandidate> = self
+            .find_fuzzy_city_matches(city, state, country)
+            .into_iter()
+            .map(|(city_id, distance)| {
+                let city_record = self.get_city_by_id(city_id).unwrap();
+                let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+                let mut reasons = vec![MatchReason::FuzzyDistance(distance)];
+                if is_iso_country_code(country_record, country) {
+                    reasons.push(MatchReason::IsoCountryCode);
+                }
+                StagedCandidate {
+                    stage: CandidateStage::Fuzzy,
+                    candidate: LocationCandidate {
+                        location: LocationMatchType::FuzzyMatch {
+                            city: city_record.id,
+                            state: city_record.state_id,
+                            country: city_record.country_id,
+                            distance,
+                        },
+                        score: (SCORE_FUZZY - SCORE_FUZZY_PER_EDIT_PENALTY * distance as f64)
+                            .max(0.0),
+                        reasons,
+                    },
+                }
+            })
+            .collect();
+        if self.fuzzy_match_config.is_some() {
+            trace.stage_candidates(
+                MatchStage::Fuzzy,
+                staged_location_matches(&staged_candidates),
+            );
+        }
+        staged_candidates
+    }
+
+    fn list_state_candidates(
+        &self,
+        state: &str,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let state_map_key = location_key(None, Some(state), Some(country));
+        let staged_candidates: Vec<StagedCandidate> = self
+            .find_name_ids_traced(
+                &self.state_name_map,
+                &state_map_key,
+                MatchStage::State,
+                trace,
+            )
+            .into_iter()
+            .flatten()
+            .map(|state_id| {
+                let state_record = self.get_state_by_id(*state_id).unwrap();
+                let country_record = self.get_country_by_id(state_record.country_id).unwrap();
+                let mut reasons = vec![MatchReason::StateOnly];
+                if !self
+                    .list_state_location_keys(state_record, None)
+                    .contains(&state_map_key)
+                {
+                    reasons.push(MatchReason::PlaceAlias);
+                }
+                if state == normalize_location_str(&state_record.state_code) {
+                    reasons.push(MatchReason::StateCode);
+                }
+                if is_iso_country_code(country_record, country) {
+                    reasons.push(MatchReason::IsoCountryCode);
+                }
+                if country.is_empty() {
+                    reasons.push(MatchReason::CountryMissing);
+                }
+                StagedCandidate {
+                    stage: CandidateStage::StateOnly,
+                    candidate: LocationCandidate {
+                        location: LocationMatchType::StateMatch {
+                            state: state_record.id,
+                            country: state_record.country_id,
+                        },
+                        score: SCORE_STATE_ONLY,
+                        reasons,
+                    },
+                }
+            })
+            .collect();
+        trace.stage_candidates(
+            MatchStage::State,
+            staged_location_matches(&staged_candidates),
+        );
+        staged_candidates
+    }
+
+    fn list_country_candidates(
+        &self,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> Vec<StagedCandidate> {
+        let country_map_key = location_key(None, None, Some(country));
+        let staged_candidates: Vec<StagedCandidate> = self
+            .find_name_ids_traced(
+                &self.country_name_map,
+                &country_map_key,
+                MatchStage::Country,
+                trace,
+            )
+            .into_iter()
+            .flatten()
+            .map(|country_id| {
+                let country_record = self.get_country_by_id(*country_id).unwrap();
+                let mut reasons = vec![MatchReason::CountryOnly];
+                if is_iso_country_code(country_record, country) {
+                    reasons.push(MatchReason::IsoCountryCode);
+                }
+                StagedCandidate {
+                    stage: CandidateStage::CountryOnly,
+                    candidate: LocationCandidate {
+                        location: LocationMatchType::CountryMatch {
+                            country: country_record.id,
+                        },
+                        score: SCORE_COUNTRY_ONLY,
+                        reasons,
+                    },
+                }
+            })
+            .collect();
+        trace.stage_candidates(
+            MatchStage::Country,
+            staged_location_matches(&staged_candidates),
+        );
+        staged_candidates
+    }
+
+    /// Works out which parts of the key generation in
+    /// `list_city_location_keys` produced `location_key` for `city_record`,
+    /// and records the place alias rule when an alias was needed.
+    fn city_key_match(
+        &self,
+        city_record: &LocationCity,
+        location_key: &NormalizedKey,
+        state: &str,
+        country: &str,
+        trace: &mut LocationTrace,
+    ) -> CityKeyMatch<'_> {
+        let place_alias = self.find_city_key_alias(city_record.id, location_key);
+        if let Some(place_alias) = place_alias {
+            trace.rule_fired(|| MatchRule::PlaceAlias {
+                city: city_record.id,
+                alias: place_alias.clone(),
+            });
+        }
+        let state_record = self.get_state_by_id(city_record.state_id).unwrap();
+        let country_record = self.get_country_by_id(city_record.country_id).unwrap();
+        CityKeyMatch {
+            place_alias,
+            state_code: !state.is_empty()
+                && state == normalize_location_str(&state_record.state_code),
+            country_code: country_code_type(country_record, country),
+            country_missing: country.is_empty(),
+        }
+    }
+}
+
+/// The normalized native country name, if it is present and differs from the
+/// normalized English name.
+fn normalized_native_name(country_record: &LocationCountry) -> Option<String> {
+    let country_native = normalize_location_str(&country_record.native);
+    if country_native.is_empty() || country_native == normalize_location_str(country_record.name())
+    {
+        return None;
+    }
+    Some(country_native)
+}
+
+/// Token similarity below this counts as no match for that token.
+const MIN_STATE_TOKEN_SIMILARITY: f64 = 0.7;
+
+/// Similarity of two normalized names between 0 and 1: each word is paired
+/// with its most similar word in the other name (by edit distance relative
+/// to length), and the coverage of both names is averaged. "Baden" against
+/// "Baden-Württemberg" scores 0.75, "Hesse" against "Hessen" 0.83.
+fn name_similarity(a: &str, b: &str) -> f64 {
+    let a_words: Vec<&str> = a.split('_').collect();
+    let b_words: Vec<&str> = b.split('_').collect();
+    let coverage = |words: &[&str], other_words: &[&str]| {
+        words
+            .iter()
+            .map(|word| {
+                other_words
+                    .iter()
+                    .map(|other_word| word_similarity(word, other_word))
+                    .filter(|similarity| *similarity >= MIN_STATE_TOKEN_SIMILARITY)
+                    .fold(0.0, f64::max)
+            })
+            .sum::<f64>()
+            / words.len() as f64
+    };
+    (coverage(&a_words, &b_words) + coverage(&b_words, &a_words)) / 2.0
+}
+
+fn word_similarity(a: &str, b: &str) -> f64 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein_distance(a, b) as f64 / max_len as f64
+}
+
+/// The words of a normalized name, sorted, so that word order does not
+/// matter when comparing.
+fn sorted_words(normalized_name: &str) -> String {
+    let mut words: Vec<&str> = normalized_name.split('_').collect();
+    words.sort_unstable();
+    words.join("_")
+}
+
+/// Parses an ISO 3166-1 numeric code such as "276" or "040".
+fn parse_numeric_code(country_in: &str) -> Option<u32> {
+    let country_in = country_in.trim();
+    if country_in.is_empty() || !country_in.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    country_in.parse().ok()
+}
+
+fn is_iso_country_code(country_record: &LocationCountry, country: &str) -> bool {
+    country_code_type(country_record, country).is_some()
+}
+
+fn country_code_type(country_record: &LocationCountry, country: &str) -> Option<CountryCodeType> {
+    if country == normalize_location_str(&country_record.iso2) {
+        Some(CountryCodeType::Iso2)
+    } else if country == normalize_location_str(&country_record.iso3) {
+        Some(CountryCodeType::Iso3)
+    } else {
+        None
+    }
+}
+
+/// Applies the alias penalty and splits the stage score between the
+/// candidates of this stage and those already produced by better stages
+/// before adding them to `candidates`.
+fn push_stage_candidates(
+    candidates: &mut Vec<LocationCandidate>,
+    stage_candidates: Vec<LocationCandidate>,
+) {
+    let competitor_count = (candidates.len() + stage_candidates.len()) as f64;
+    for mut candidate in stage_candidates {
+        if candidate.reasons.contains(&MatchReason::PlaceAlias) {
+            candidate.score -= SCORE_PLACE_ALIAS_PENALTY;
         }
+        candidate.score /= competitor_count;
+        candidates.push(candidate);
     }
-    Ok(LocationMatchType::NoMatch)
 }
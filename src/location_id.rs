@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+macro_rules! location_id {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(
+            Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub u64);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(id: u64) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for u64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+    };
+}
+
+location_id!(
+    /// The `id` of a row in `cities.csv`.
+    CityId
+);
+location_id!(
+    /// The `id` of a row in `states.csv`.
+    StateId
+);
+location_id!(
+    /// The `id` of a row in `countries.csv`.
+    CountryId
+);
+
+/// A city, state or country.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PlaceId {
+    City(CityId),
+    State(StateId),
+    Country(CountryId),
+}
+
+impl fmt::Display for PlaceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaceId::City(id) => write!(f, "city {}", id),
+            PlaceId::State(id) => write!(f, "state {}", id),
+            PlaceId::Country(id) => write!(f, "country {}", id),
+        }
+    }
+}
+
+impl From<CityId> for PlaceId {
+    fn from(id: CityId) -> Self {
+        PlaceId::City(id)
+    }
+}
+
+impl From<StateId> for PlaceId {
+    fn from(id: StateId) -> Self {
+        PlaceId::State(id)
+    }
+}
+
+impl From<CountryId> for PlaceId {
+    fn from(id: CountryId) -> Self {
+        PlaceId::Country(id)
+    }
+}
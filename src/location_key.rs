@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+/// The normalized city, state and country parts of a name index key. A level
+/// that was not given, or normalized to an empty string, is `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NormalizedKey {
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+}
+
+impl NormalizedKey {
+    pub fn new(city: Option<&str>, state: Option<&str>, country: Option<&str>) -> NormalizedKey {
+        let level = |part: Option<&str>| {
+            part.filter(|part| !part.is_empty())
+                .map(|part| part.to_string())
+        };
+        NormalizedKey {
+            city: level(city),
+            state: level(state),
+            country: level(country),
+        }
+    }
+}
+
+/// An interned normalized name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NameSymbol(u32);
+
+/// The key stored in the name index: one interned symbol per level, so keys
+/// are small, fixed-size and cannot collide across levels the way
+/// underscore-joined strings did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocationKey {
+    pub city: Option<NameSymbol>,
+    pub state: Option<NameSymbol>,
+    pub country: Option<NameSymbol>,
+}
+
+/// Maps each distinct normalized name to a [`NameSymbol`].
+#[derive(Default)]
+pub struct NameInterner {
+    symbols: HashMap<String, NameSymbol>,
+}
+
+impl NameInterner {
+    pub fn intern(&mut self, name: &str) -> NameSymbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = NameSymbol(self.symbols.len() as u32);
+        self.symbols.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    pub fn get(&self, name: &str) -> Option<NameSymbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn intern_key(&mut self, normalized_key: &NormalizedKey) -> LocationKey {
+        LocationKey {
+            city: normalized_key.city.as_deref().map(|name| self.intern(name)),
+            state: normalized_key
+                .state
+                .as_deref()
+                .map(|name| self.intern(name)),
+            country: normalized_key
+                .country
+                .as_deref()
+                .map(|name| self.intern(name)),
+        }
+    }
+
+    /// Returns the index key for `normalized_key`, or `None` if one of its
+    /// names was never interned, in which case nothing can match it.
+    pub fn lookup_key(&self, normalized_key: &NormalizedKey) -> Option<LocationKey> {
+        let lookup = |name: &Option<String>| match name {
+            Some(name) => self.get(name).map(Some),
+            None => Some(None),
+        };
+        Some(LocationKey {
+            city: lookup(&normalized_key.city)?,
+            state: lookup(&normalized_key.state)?,
+            country: lookup(&normalized_key.country)?,
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use crate::location_finder::{LocationIndex, LocationMatchType, MatchOrigin};
+
+/// Inputs longer than this are truncated before trying splits, since the
+/// number of splits grows quadratically with the token count.
+const MAX_LOCATION_TOKENS: usize = 12;
+
+/// Score multiplier for each level boundary that does not fall on a
+/// separator in the input, or each level that spans a separator.
+const SPLIT_PENALTY: f64 = 0.95;
+
+/// Score multiplier for each trailing word dropped from the input.
+const IGNORED_TOKEN_PENALTY: f64 = 0.9;
+
+/// The best reading of a free-form location string: which part of the input
+/// was taken as the city, state and country, and what it matched.
+#[derive(Debug, Clone)]
+pub struct LocationInterpretation {
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    /// Trailing words that were not read as any level, e.g. "Bay Area" in
+    /// "San Francisco Bay Area".
+    pub ignored: Option<String>,
+    pub location: LocationMatchType,
+    pub score: f64,
+}
+
+struct LocationToken<'a> {
+    text: &'a str,
+    segment: usize,
+}
+
+/// Splits on commas, semicolons and parentheses into segments and then on
+/// whitespace into tokens, remembering which segment each token came from.
+fn tokenize_location_str(location_str: &str) -> Vec<LocationToken<'_>> {
+    location_str
+        .split([',', ';', '(', ')', '|'])
+        .map(|segment| segment.trim())
+        .filter(|segment| !segment.is_empty())
+        .enumerate()
+        .flat_map(|(segment_index, segment)| {
+            segment.split_whitespace().map(move |text| LocationToken {
+                text,
+                segment: segment_index,
+            })
+        })
+        .take(MAX_LOCATION_TOKENS)
+        .collect()
+}
+
+fn join_tokens(tokens: &[LocationToken]) -> Option<String> {
+    if tokens.is_empty() {
+        return None;
+    }
+    Some(
+        tokens
+            .iter()
+            .map(|token| token.text)
+            .collect::<Vec<&str>>()
+            .join(" "),
+    )
+}
+
+/// Counts separators crossed inside a level plus level boundaries that fall
+/// inside a segment.
+fn count_split_violations(tokens: &[LocationToken], boundaries: &[usize]) -> i32 {
+    let mut violations = 0;
+    let mut level_start = 0;
+    for &boundary in boundaries.iter().chain(std::iter::once(&tokens.len())) {
+        let level_tokens = &tokens[level_start..boundary];
+        if let (Some(first), Some(last)) = (level_tokens.first(), level_tokens.last()) {
+            violations += (last.segment - first.segment) as i32;
+        }
+        if boundary > 0
+            && boundary < tokens.len()
+            && boundary != level_start
+            && tokens[boundary - 1].segment == tokens[boundary].segment
+        {
+            violations += 1;
+        }
+        level_start = boundary;
+    }
+    violations
+}
+
+/// A reading where the city and country matched but the state text did not,
+/// either reported as such or promoted by the country's partial-match policy.
+fn is_state_mismatch(location_match: &LocationMatchType) -> bool {
+    match location_match {
+        LocationMatchType::PartialMatch { .. } => true,
+        LocationMatchType::FullMatch { provenance, .. } => {
+            provenance.origin == MatchOrigin::CountryOverride
+        }
+        _ => false,
+    }
+}
+
+fn is_full_match(location_match: &LocationMatchType) -> bool {
+    matches!(location_match, LocationMatchType::FullMatch { .. })
+        && !is_state_mismatch(location_match)
+}
+
+impl LocationIndex {
+    /// Parses a single free-form string such as "Munich, Bavaria, DE",
+    /// "Berlin Germany", "London (GB)" or "Munich, Bavaria". Every split of
+    /// the tokens into city, state and country (the country being the
+    /// trailing part, which may be empty) is scored with
+    /// [`LocationIndex::find_location_candidates`]; splits that disagree with
+    /// the commas and parentheses in the input are penalized. Splits of the
+    /// input with trailing words dropped are scored too, with a penalty per
+    /// dropped word, so that "San Francisco Bay Area" is read as
+    /// "San Francisco". A reading whose state did not match is only kept
+    /// when no reading is a full match. Returns `None` when nothing matches.
+    pub fn find_location_str(&self, location_str: &str) -> Option<LocationInterpretation> {
+        let tokens = tokenize_location_str(location_str);
+        let mut interpretations = Vec::new();
+        for token_count in (1..=tokens.len()).rev() {
+            let ignored = join_tokens(&tokens[token_count..]);
+            let ignored_penalty = IGNORED_TOKEN_PENALTY.powi((tokens.len() - token_count) as i32);
+            for (mut interpretation, level_count) in self.list_splits(&tokens[..token_count]) {
+                interpretation.ignored = ignored.clone();
+                interpretation.score *= ignored_penalty;
+                interpretations.push((interpretation, level_count));
+            }
+        }
+        let any_full_match = interpretations
+            .iter()
+            .any(|(interpretation, _)| is_full_match(&interpretation.location));
+        let mut best: Option<(LocationInterpretation, usize)> = None;
+        for (interpretation, level_count) in interpretations {
+            if any_full_match && is_state_mismatch(&interpretation.location) {
+                continue;
+            }
+            let is_better = match &best {
+                None => true,
+                Some((best_interpretation, best_level_count)) => {
+                    interpretation.score > best_interpretation.score
+                        || (interpretation.score == best_interpretation.score
+                            && level_count > *best_level_count)
+                }
+            };
+            if is_better {
+                best = Some((interpretation, level_count));
+            }
+        }
+        best.map(|(interpretation, _)| interpretation)
+    }
+
+    /// Scores every split of `tokens` that matches anything, along with the
+    /// number of levels it fills.
+    fn list_splits(&self, tokens: &[LocationToken]) -> Vec<(LocationInterpretation, usize)> {
+        let mut interpretations = Vec::new();
+        for country_start in 0..=tokens.len() {
+            for state_start in 0..=country_start {
+                let city = join_tokens(&tokens[..state_start]);
+                let state = join_tokens(&tokens[state_start..country_start]);
+                let country = join_tokens(&tokens[country_start..]);
+                let Some(candidate) = self
+                    .find_location_candidates(
+                        city.as_deref().unwrap_or(""),
+                        state.as_deref().unwrap_or(""),
+                        country.as_deref().unwrap_or(""),
+                        1,
+                    )
+                    .into_iter()
+                    .next()
+                else {
+                    continue;
+                };
+                let violations = count_split_violations(tokens, &[state_start, country_start]);
+                let level_count = [&city, &state, &country]
+                    .iter()
+                    .filter(|level| level.is_some())
+                    .count();
+                interpretations.push((
+                    LocationInterpretation {
+                        city,
+                        state,
+                        country,
+                        ignored: None,
+                        location: candidate.location,
+                        score: candidate.score * SPLIT_PENALTY.powi(violations),
+                    },
+                    level_count,
+                ));
+            }
+        }
+        interpretations
+    }
+}
@@ -0,0 +1,231 @@
+use crate::error::LocationFinderError;
+use crate::explain::{match_place_id, LocationTrace, MatchRule};
+use crate::location_finder::{LocationCandidate, LocationIndex, LocationMatchType};
+use crate::location_id::CountryId;
+use crate::spatial::haversine_distance_km;
+use serde::Serialize;
+
+/// A structured location lookup. Fields left as `None` are treated as
+/// unknown: a missing state is ignored and a missing country (with no
+/// default country) searches all countries. The hints never turn a
+/// non-match into a match; they only choose between equally good matches.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LocationQuery {
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    /// Kept with the query for callers that have it. The dataset has no
+    /// postal codes, so it does not affect matching.
+    pub postal_code: Option<String>,
+    /// Country used when `country` is not given.
+    pub default_country: Option<String>,
+    /// Among tied matches, prefer the one closest to this
+    /// `(latitude, longitude)`.
+    pub bias_coordinate: Option<(f64, f64)>,
+    /// Among tied matches, prefer these countries, earliest first.
+    pub preferred_countries: Vec<String>,
+}
+
+impl LocationQuery {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn city(mut self, city: &str) -> Self {
+        self.city = Some(city.to_string());
+        self
+    }
+
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_string());
+        self
+    }
+
+    pub fn country(mut self, country: &str) -> Self {
+        self.country = Some(country.to_string());
+        self
+    }
+
+    pub fn postal_code(mut self, postal_code: &str) -> Self {
+        self.postal_code = Some(postal_code.to_string());
+        self
+    }
+
+    pub fn default_country(mut self, country: &str) -> Self {
+        self.default_country = Some(country.to_string());
+        self
+    }
+
+    pub fn bias_coordinate(mut self, latitude: f64, longitude: f64) -> Self {
+        self.bias_coordinate = Some((latitude, longitude));
+        self
+    }
+
+    pub fn preferred_countries<'c>(mut self, countries: impl IntoIterator<Item = &'c str>) -> Self {
+        self.preferred_countries = countries
+            .into_iter()
+            .map(|country| country.to_string())
+            .collect();
+        self
+    }
+
+    fn country_or_default(&self) -> Option<&str> {
+        self.country.as_deref().or(self.default_country.as_deref())
+    }
+}
+
+/// How well a match fits the query hints; lower is better.
+#[derive(Debug, Clone, Copy)]
+struct HintRank {
+    preferred_country: usize,
+    distance_km: f64,
+}
+
+impl LocationIndex {
+    /// Like [`LocationIndex::find_location`], but takes a [`LocationQuery`].
+    /// When several records match equally well, the preferred countries and
+    /// then the bias coordinate narrow them down before the result is
+    /// reported as [`LocationMatchType::Ambiguous`].
+    pub fn find_location_query(
+        &self,
+        query: &LocationQuery,
+    ) -> Result<LocationMatchType, LocationFinderError> {
+        self.find_location_query_traced(query, &mut LocationTrace::default())
+    }
+
+    pub(crate) fn find_location_query_traced(
+        &self,
+        query: &LocationQuery,
+        trace: &mut LocationTrace,
+    ) -> Result<LocationMatchType, LocationFinderError> {
+        if query.country.is_none() {
+            if let Some(default_country) = &query.default_country {
+                trace.rule_fired(|| MatchRule::DefaultCountry {
+                    country: default_country.clone(),
+                });
+            }
+        }
+        let location_match = self.find_location_traced(
+            query.city.as_deref().unwrap_or(""),
+            query.state.as_deref().unwrap_or(""),
+            query.country_or_default().unwrap_or(""),
+            trace,
+        )?;
+        let LocationMatchType::Ambiguous { candidates } = location_match else {
+            return Ok(location_match);
+        };
+        let hint_ranks: Vec<HintRank> = candidates
+            .iter()
+            .map(|candidate| self.hint_rank(candidate, query))
+            .collect();
+        let Some(best_rank) = hint_ranks.iter().copied().min_by(compare_hint_ranks) else {
+            return Ok(LocationMatchType::NoMatch);
+        };
+        let kept_candidates: Vec<LocationMatchType> = candidates
+            .iter()
+            .zip(hint_ranks)
+            .filter(|(_, hint_rank)| compare_hint_ranks(hint_rank, &best_rank).is_eq())
+            .map(|(candidate, _)| candidate.clone())
+            .collect();
+        if kept_candidates.len() < candidates.len() {
+            trace.rule_fired(|| MatchRule::HintTieBreak {
+                candidates: candidates.iter().filter_map(match_place_id).collect(),
+                kept: kept_candidates.iter().filter_map(match_place_id).collect(),
+            });
+        }
+        Ok(LocationMatchType::from_candidates(kept_candidates))
+    }
+
+    /// Like [`LocationIndex::find_location_candidates`], but takes a
+    /// [`LocationQuery`]. Candidates with equal scores are ordered by the
+    /// query hints before the usual tie-break.
+    pub fn find_location_query_candidates(
+        &self,
+        query: &LocationQuery,
+        limit: usize,
+    ) -> Vec<LocationCandidate> {
+        let candidates = self.list_location_candidates(
+            query.city.as_deref().unwrap_or(""),
+            query.state.as_deref().unwrap_or(""),
+            query.country_or_default().unwrap_or(""),
+        );
+        let mut ranked_candidates: Vec<(LocationCandidate, HintRank)> = candidates
+            .into_iter()
+            .map(|candidate| {
+                let hint_rank = self.hint_rank(&candidate.location, query);
+                (candidate, hint_rank)
+            })
+            .collect();
+        ranked_candidates.sort_by(|(a, a_rank), (b, b_rank)| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| compare_hint_ranks(a_rank, b_rank))
+                .then_with(|| a.location.tie_break_key().cmp(&b.location.tie_break_key()))
+        });
+        ranked_candidates
+            .into_iter()
+            .take(limit)
+            .map(|(candidate, _)| candidate)
+            .collect()
+    }
+
+    fn hint_rank(&self, location_match: &LocationMatchType, query: &LocationQuery) -> HintRank {
+        let (country_id, coordinate) = self.match_country_and_coordinate(location_match);
+        let preferred_country = query
+            .preferred_countries
+            .iter()
+            .position(|preferred_country| {
+                self.find_country(preferred_country)
+                    .is_some_and(|country_record| Some(country_record.id) == country_id)
+            })
+            .unwrap_or(query.preferred_countries.len());
+        let distance_km = match (query.bias_coordinate, coordinate) {
+            (Some((bias_latitude, bias_longitude)), Some((latitude, longitude))) => {
+                haversine_distance_km(bias_latitude, bias_longitude, latitude, longitude)
+            }
+            _ => f64::INFINITY,
+        };
+        HintRank {
+            preferred_country,
+            distance_km,
+        }
+    }
+
+    /// The country and the coordinate of the most specific record in a match.
+    fn match_country_and_coordinate(
+        &self,
+        location_match: &LocationMatchType,
+    ) -> (Option<CountryId>, Option<(f64, f64)>) {
+        match location_match {
+            LocationMatchType::FullMatch { city, country, .. }
+            | LocationMatchType::SimilarStateMatch { city, country, .. }
+            | LocationMatchType::PartialMatch { city, country, .. }
+            | LocationMatchType::TokenSetMatch { city, country, .. }
+            | LocationMatchType::FuzzyMatch { city, country, .. } => {
+                let coordinate = self
+                    .get_city_by_id(*city)
+                    .and_then(|city_record| city_record.latitude.zip(city_record.longitude));
+                (Some(*country), coordinate)
+            }
+            LocationMatchType::StateMatch { state, country } => {
+                let coordinate = self
+                    .get_state_by_id(*state)
+                    .and_then(|state_record| state_record.latitude.zip(state_record.longitude));
+                (Some(*country), coordinate)
+            }
+            LocationMatchType::CountryMatch { country } => {
+                let coordinate = self
+                    .get_country_by_id(*country)
+                    .map(|country_record| (country_record.latitude, country_record.longitude));
+                (Some(*country), coordinate)
+            }
+            LocationMatchType::Ambiguous { .. } | LocationMatchType::NoMatch => (None, None),
+        }
+    }
+}
+
+fn compare_hint_ranks(a: &HintRank, b: &HintRank) -> std::cmp::Ordering {
+    a.preferred_country
+        .cmp(&b.preferred_country)
+        .then_with(|| a.distance_km.total_cmp(&b.distance_km))
+}
@@ -0,0 +1,93 @@
+use crate::location_finder::{
+    LocationCity, LocationCountry, LocationIndex, LocationMatchType, LocationState,
+};
+use serde::Serialize;
+
+/// A single match resolved to its records. `city` and `state` are only set
+/// when the match reached that level; for a partial match `state` is the
+/// city's own state, which did not match the input.
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchedLocation {
+    pub city: Option<LocationCity>,
+    pub state: Option<LocationState>,
+    pub country: LocationCountry,
+    /// The coordinates of the most specific record that has them; every
+    /// country has coordinates.
+    pub latitude: f64,
+    pub longitude: f64,
+    pub country_code_iso2: String,
+    pub country_code_iso3: String,
+    pub state_code: Option<String>,
+    /// The city's Wikidata ID, if the match reached a city that has one.
+    pub wikidata_id: Option<String>,
+    /// The match the records were resolved from, with its provenance,
+    /// similarity or edit distance.
+    pub location_match: LocationMatchType,
+}
+
+impl LocationIndex {
+    /// Resolves a match to its records. Returns `None` for
+    /// [`LocationMatchType::Ambiguous`] and [`LocationMatchType::NoMatch`];
+    /// call [`LocationMatchType::resolve`] first to pick one of several
+    /// candidates.
+    pub fn matched_location(&self, location_match: &LocationMatchType) -> Option<MatchedLocation> {
+        let (city_id, state_id, country_id) = match location_match {
+            LocationMatchType::FullMatch {
+                city,
+                state,
+                country,
+                ..
+            }
+            | LocationMatchType::SimilarStateMatch {
+                city,
+                state,
+                country,
+                ..
+            }
+            | LocationMatchType::TokenSetMatch {
+                city,
+                state,
+                country,
+            }
+            | LocationMatchType::FuzzyMatch {
+                city,
+                state,
+                country,
+                ..
+            }
+            | LocationMatchType::PartialMatch {
+                city,
+                country,
+                unmatched_state: state,
+            } => (Some(*city), Some(*state), *country),
+            LocationMatchType::StateMatch { state, country } => (None, Some(*state), *country),
+            LocationMatchType::CountryMatch { country } => (None, None, *country),
+            LocationMatchType::Ambiguous { .. } | LocationMatchType::NoMatch => return None,
+        };
+        let city_record = city_id.and_then(|city_id| self.get_city_by_id(city_id));
+        let state_record = state_id.and_then(|state_id| self.get_state_by_id(state_id));
+        let country_record = self.get_country_by_id(country_id)?;
+        let (latitude, longitude) = city_record
+            .and_then(|city_record| city_record.latitude.zip(city_record.longitude))
+            .or_else(|| {
+                state_record
+                    .and_then(|state_record| state_record.latitude.zip(state_record.longitude))
+            })
+            .unwrap_or((country_record.latitude, country_record.longitude));
+        Some(MatchedLocation {
+            city: city_record.cloned(),
+            state: state_record.cloned(),
+            country: country_record.clone(),
+            latitude,
+            longitude,
+            country_code_iso2: country_record.iso2.clone(),
+            country_code_iso3: country_record.iso3.clone(),
+            state_code: state_record.map(|state_record| state_record.state_code.clone()),
+            wikidata_id: city_record
+                .map(|city_record| city_record.wiki_data_id.trim())
+                .filter(|wikidata_id| !wikidata_id.is_empty())
+                .map(|wikidata_id| wikidata_id.to_string()),
+            location_match: location_match.clone(),
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What to do with a city that matched by name and country but whose state
+/// did not match the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialMatchPolicy {
+    /// Drop the match; city names repeat across states too often for a
+    /// mismatched state to be trusted.
+    Skip,
+    /// Report a full match; states are not meaningful enough in this
+    /// country to reject the city.
+    Override,
+    /// Report a similar-state match when the state input is similar enough
+    /// to the state's name, code or an alias, otherwise a partial match.
+    StateSimilarity,
+    /// Report a partial match; only an exact state counts as a full match.
+    RequireExactState,
+}
+
+impl PartialMatchPolicy {
+    /// Parses the name used in policy files: `skip`, `override`,
+    /// `state_similarity` or `require_exact_state`.
+    pub fn from_name(name: &str) -> Option<PartialMatchPolicy> {
+        match name {
+            "skip" => Some(PartialMatchPolicy::Skip),
+            "override" => Some(PartialMatchPolicy::Override),
+            "state_similarity" => Some(PartialMatchPolicy::StateSimilarity),
+            "require_exact_state" => Some(PartialMatchPolicy::RequireExactState),
+            _ => None,
+        }
+    }
+}
+
+/// The partial-match policy for each country, keyed by ISO2 code, with a
+/// fallback for countries that have no entry. The default skips partial
+/// matches in the US, overrides them in the UK and applies the state
+/// similarity heuristic everywhere else.
+#[derive(Debug, Clone)]
+pub struct PartialMatchPolicies {
+    default_policy: PartialMatchPolicy,
+    by_country: HashMap<String, PartialMatchPolicy>,
+}
+
+impl Default for PartialMatchPolicies {
+    fn default() -> Self {
+        let mut partial_match_policies =
+            PartialMatchPolicies::new(PartialMatchPolicy::StateSimilarity);
+        partial_match_policies
+            .set("US", PartialMatchPolicy::Skip)
+            .set("GB", PartialMatchPolicy::Override);
+        partial_match_policies
+    }
+}
+
+impl PartialMatchPolicies {
+    /// A table with no country entries, applying `default_policy` everywhere.
+    pub fn new(default_policy: PartialMatchPolicy) -> PartialMatchPolicies {
+        PartialMatchPolicies {
+            default_policy,
+            by_country: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, country_code_iso2: &str, policy: PartialMatchPolicy) -> &mut Self {
+        self.by_country
+            .insert(country_code_iso2.to_ascii_uppercase(), policy);
+        self
+    }
+
+    pub fn set_default(&mut self, policy: PartialMatchPolicy) -> &mut Self {
+        self.default_policy = policy;
+        self
+    }
+
+    pub fn get(&self, country_code_iso2: &str) -> PartialMatchPolicy {
+        self.by_country
+            .get(&country_code_iso2.to_ascii_uppercase())
+            .copied()
+            .unwrap_or(self.default_policy)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::location_id::CityId;
+
+/// Mean Earth radius used for great-circle distances.
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// A city and its great-circle distance from a query point.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct CityDistance {
+    pub city: CityId,
+    pub distance_km: f64,
+}
+
+/// Great-circle distance between two points given in degrees, using the
+/// haversine formula.
+pub fn haversine_distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lon1, lat2, lon2) = (
+        lat1.to_radians(),
+        lon1.to_radians(),
+        lat2.to_radians(),
+        lon2.to_radians(),
+    );
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Whether a query point is on the globe: both coordinates finite and the
+/// latitude within ±90 degrees. Longitudes wrap, so any finite value is fine.
+fn is_valid_coordinate(latitude: f64, longitude: f64) -> bool {
+    latitude.is_finite() && longitude.is_finite() && latitude.abs() <= 90.0
+}
+
+fn to_unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn chord_distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// Straight-line distance through the unit sphere between two points that
+/// are `distance_km` apart on the surface.
+fn chord_length(distance_km: f64) -> f64 {
+    let angle = (distance_km / EARTH_RADIUS_KM).min(std::f64::consts::PI);
+    2.0 * (angle / 2.0).sin()
+}
+
+struct SpatialPoint {
+    position: [f64; 3],
+    latitude: f64,
+    longitude: f64,
+    city: CityId,
+}
+
+/// A static k-d tree over city coordinates projected onto the unit sphere.
+/// Chord length is monotonic in great-circle distance, so nearest-neighbour
+/// and radius searches in 3D give exact answers without special cases for
+/// the antimeridian or the poles.
+#[derive(Default)]
+pub(crate) struct SpatialIndex {
+    points: Vec<SpatialPoint>,
+}
+
+impl SpatialIndex {
+    /// Builds the tree from `(city_id, latitude, longitude)` triples.
+    pub fn new(cities: impl IntoIterator<Item = (CityId, f64, f64)>) -> SpatialIndex {
+        let mut points: Vec<SpatialPoint> = cities
+            .into_iter()
+            .map(|(city, latitude, longitude)| SpatialPoint {
+                position: to_unit_vector(latitude, longitude),
+                latitude,
+                longitude,
+                city,
+            })
+            .collect();
+        // Sorting first makes the layout, and so tie-breaks, independent of
+        // the input order.
+        points.sort_by_key(|point| point.city);
+        build_subtree(&mut points, 0);
+        SpatialIndex { points }
+    }
+
+    pub fn nearest(&self, latitude: f64, longitude: f64) -> Option<CityDistance> {
+        if !is_valid_coordinate(latitude, longitude) {
+            return None;
+        }
+        let target = to_unit_vector(latitude, longitude);
+        let mut best: Option<(f64, usize)> = None;
+        self.nearest_in(&target, 0, self.points.len(), 0, &mut best);
+        best.map(|(_, index)| self.city_distance(index, latitude, longitude))
+    }
+
+    /// Returns every city within `radius_km`, closest first.
+    pub fn within(&self, latitude: f64, longitude: f64, radius_km: f64) -> Vec<CityDistance> {
+        if !is_valid_coordinate(latitude, longitude) {
+            return vec![];
+        }
+        let target = to_unit_vector(latitude, longitude);
+        let max_chord = chord_length(radius_km);
+        let mut found = Vec::new();
+        self.within_in(
+            &target,
+            max_chord * max_chord,
+            0,
+            self.points.len(),
+            0,
+            &mut found,
+        );
+        let mut city_distances: Vec<CityDistance> = found
+            .into_iter()
+            .map(|index| self.city_distance(index, latitude, longitude))
+            .filter(|city_distance| city_distance.distance_km <= radius_km)
+            .collect();
+        city_distances.sort_by(|a, b| {
+            a.distance_km
+                .total_cmp(&b.distance_km)
+                .then_with(|| a.city.cmp(&b.city))
+        });
+        city_distances
+    }
+
+    fn city_distance(&self, index: usize, latitude: f64, longitude: f64) -> CityDistance {
+        let point = &self.points[index];
+        CityDistance {
+            city: point.city,
+            distance_km: haversine_distance_km(
+                latitude,
+                longitude,
+                point.latitude,
+                point.longitude,
+            ),
+        }
+    }
+
+    fn nearest_in(
+        &self,
+        target: &[f64; 3],
+        start: usize,
+        end: usize,
+        depth: usize,
+        best: &mut Option<(f64, usize)>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let point = &self.points[mid];
+        let distance = chord_distance_squared(&point.position, target);
+        if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+            *best = Some((distance, mid));
+        }
+        let axis = depth % 3;
+        let delta = target[axis] - point.position[axis];
+        let (near, far) = if delta < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.nearest_in(target, near.0, near.1, depth + 1, best);
+        if best.is_none_or(|(best_distance, _)| delta * delta < best_distance) {
+            self.nearest_in(target, far.0, far.1, depth + 1, best);
+        }
+    }
+
+    fn within_in(
+        &self,
+        target: &[f64; 3],
+        max_distance: f64,
+        start: usize,
+        end: usize,
+        depth: usize,
+        found: &mut Vec<usize>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let point = &self.points[mid];
+        if chord_distance_squared(&point.position, target) <= max_distance {
+            found.push(mid);
+        }
+        let axis = depth % 3;
+        let delta = target[axis] - point.position[axis];
+        if delta < 0.0 || delta * delta <= max_distance {
+            self.within_in(target, max_distance, start, mid, depth + 1, found);
+        }
+        if delta >= 0.0 || delta * delta <= max_distance {
+            self.within_in(target, max_distance, mid + 1, end, depth + 1, found);
+        }
+    }
+}
+
+/// Arranges `points` in place so that each range's middle element splits it
+/// on the axis for its depth.
+fn build_subtree(points: &mut [SpatialPoint], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    let (left, right) = points.split_at_mut(mid);
+    build_subtree(left, depth + 1);
+    build_subtree(&mut right[1..], depth + 1);
+}
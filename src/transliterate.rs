@@ -0,0 +1,67 @@
+use unidecode::unidecode_char;
+
+/// Returns true for characters in the non-Latin scripts we romanize: Greek,
+/// Cyrillic, Hebrew, Arabic, Devanagari and CJK (including kana and Hangul).
+fn is_transliterated_script(c: char) -> bool {
+    matches!(c,
+        '\u{0370}'..='\u{03FF}'     // Greek and Coptic
+        | '\u{1F00}'..='\u{1FFF}'   // Greek Extended
+        | '\u{0400}'..='\u{052F}'   // Cyrillic and Cyrillic Supplement
+        | '\u{0590}'..='\u{05FF}'   // Hebrew
+        | '\u{0600}'..='\u{06FF}'   // Arabic
+        | '\u{0750}'..='\u{077F}'   // Arabic Supplement
+        | '\u{FB50}'..='\u{FDFF}'   // Arabic Presentation Forms-A
+        | '\u{FE70}'..='\u{FEFF}'   // Arabic Presentation Forms-B
+        | '\u{0900}'..='\u{097F}'   // Devanagari
+        | '\u{3040}'..='\u{30FF}'   // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK Unified Ideographs Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul Syllables
+    )
+}
+
+/// Romanizes `c` if it belongs to one of the supported non-Latin scripts.
+/// Must run before NFKD, which would otherwise split Hangul syllables into
+/// jamo.
+pub fn transliterate_char(c: char) -> Option<&'static str> {
+    if is_transliterated_script(c) {
+        Some(unidecode_char(c))
+    } else {
+        None
+    }
+}
+
+/// Romanizes Latin letters that have no Unicode decomposition and would
+/// otherwise be dropped by the ASCII filter after NFKD, e.g. "Łódź" would
+/// become "odz" instead of "lodz".
+pub fn fold_latin_char(c: char) -> Option<&'static str> {
+    let folded = match c {
+        'ł' => "l",
+        'Ł' => "L",
+        'ø' => "o",
+        'Ø' => "O",
+        'đ' => "d",
+        'Đ' => "D",
+        'ð' => "d",
+        'Ð' => "D",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ı' => "i",
+        'ħ' => "h",
+        'Ħ' => "H",
+        'ŧ' => "t",
+        'Ŧ' => "T",
+        'ŋ' => "ng",
+        'Ŋ' => "NG",
+        'ſ' => "s",
+        _ => return None,
+    };
+    Some(folded)
+}
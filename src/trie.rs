@@ -0,0 +1,75 @@
+struct TrieNode<T> {
+    values: Vec<T>,
+    children: Vec<(u8, usize)>,
+}
+
+impl<T> TrieNode<T> {
+    fn new() -> Self {
+        TrieNode {
+            values: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn child(&self, byte: u8) -> Result<usize, usize> {
+        self.children
+            .binary_search_by_key(&byte, |(child_byte, _)| *child_byte)
+    }
+}
+
+/// A byte-wise trie over string keys. Prefix lookups only visit the subtree
+/// below the prefix, so they do not depend on how many keys share no prefix
+/// with the query.
+pub struct PrefixTrie<T> {
+    nodes: Vec<TrieNode<T>>,
+}
+
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        PrefixTrie {
+            nodes: vec![TrieNode::new()],
+        }
+    }
+}
+
+impl<T: PartialEq> PrefixTrie<T> {
+    pub fn insert(&mut self, key: &str, value: T) {
+        let mut node_index = 0;
+        for byte in key.bytes() {
+            node_index = match self.nodes[node_index].child(byte) {
+                Ok(position) => self.nodes[node_index].children[position].1,
+                Err(position) => {
+                    let new_index = self.nodes.len();
+                    self.nodes.push(TrieNode::new());
+                    self.nodes[node_index]
+                        .children
+                        .insert(position, (byte, new_index));
+                    new_index
+                }
+            };
+        }
+        let values = &mut self.nodes[node_index].values;
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    /// Returns the values of every key that starts with `prefix`.
+    pub fn find_prefix(&self, prefix: &str) -> Vec<&T> {
+        let mut node_index = 0;
+        for byte in prefix.bytes() {
+            match self.nodes[node_index].child(byte) {
+                Ok(position) => node_index = self.nodes[node_index].children[position].1,
+                Err(_) => return vec![],
+            }
+        }
+        let mut found = Vec::new();
+        let mut pending = vec![node_index];
+        while let Some(node_index) = pending.pop() {
+            let node = &self.nodes[node_index];
+            found.extend(node.values.iter());
+            pending.extend(node.children.iter().map(|(_, child_index)| *child_index));
+        }
+        found
+    }
+}
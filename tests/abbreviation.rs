@@ -0,0 +1,142 @@
+mod common;
+
+use location_finder::abbreviation::AbbreviationTable;
+use location_finder::location_finder::{LocationIndex, LocationIndexBuilder, LocationMatchType};
+use location_finder::location_id::{CityId, CountryId, StateId};
+
+const EXTRA_CITIES_CSV: &str = "\
+28200,Sankt Englmar,3009,BY,Bavaria,82,DE,Germany,48.99,12.82,Q32076
+111002,Fort Bragg,1416,CA,California,233,US,United States,39.44,-123.81,Q988915
+111003,Mount Shasta,1416,CA,California,233,US,United States,41.31,-122.31,Q985235
+";
+
+/// The sample dataset plus cities whose names are usually abbreviated.
+fn abbreviation_builder(cities_csv: &str) -> LocationIndexBuilder<'_> {
+    common::sample_builder().cities(cities_csv.as_bytes())
+}
+
+fn abbreviation_cities_csv() -> String {
+    format!("{}{}", common::CITIES_CSV, EXTRA_CITIES_CSV)
+}
+
+fn full_match_city(
+    location_index: &LocationIndex,
+    city: &str,
+    state: &str,
+    country: &str,
+) -> Option<CityId> {
+    match location_index.find_location(city, state, country).unwrap() {
+        LocationMatchType::FullMatch { city, .. } => Some(city),
+        _ => None,
+    }
+}
+
+#[test]
+fn global_abbreviations_are_expanded() {
+    let cities_csv = abbreviation_cities_csv();
+    let location_index = abbreviation_builder(&cities_csv).build().unwrap();
+    assert_eq!(
+        full_match_city(&location_index, "St. Louis", "Missouri", "US"),
+        Some(CityId(111001))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "St Louis", "MO", "USA"),
+        Some(CityId(111001))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "Ft. Bragg", "California", "US"),
+        Some(CityId(111002))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "Mt Shasta", "CA", "US"),
+        Some(CityId(111003))
+    );
+}
+
+#[test]
+fn country_entries_win_over_global_entries() {
+    let cities_csv = abbreviation_cities_csv();
+    let location_index = abbreviation_builder(&cities_csv).build().unwrap();
+    // "St" is "Sankt" in Germany but still "Saint" in the United States.
+    assert_eq!(
+        full_match_city(&location_index, "St. Englmar", "Bavaria", "DE"),
+        Some(CityId(28200))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "St. Louis", "Missouri", "US"),
+        Some(CityId(111001))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "Frankfurt a. M.", "Hesse", "Germany"),
+        Some(CityId(28001))
+    );
+    // "a. M." is only expanded for Germany.
+    assert_eq!(
+        full_match_city(&location_index, "Frankfurt a. M.", "Hesse", ""),
+        None
+    );
+}
+
+#[test]
+fn a_custom_table_replaces_the_built_in_entries() {
+    let mut abbreviation_table = AbbreviationTable::new();
+    abbreviation_table
+        .add("SF", "Santa Fe")
+        .add_for_country("MX", "SF", "San Francisco");
+    let location_index = common::sample_builder()
+        .abbreviations(abbreviation_table)
+        .build()
+        .unwrap();
+    assert_eq!(
+        full_match_city(&location_index, "SF", "Jalisco", "MX"),
+        Some(CityId(130000))
+    );
+    assert_eq!(
+        full_match_city(&location_index, "SF", "California", "US"),
+        None
+    );
+    assert_eq!(
+        full_match_city(&location_index, "St. Louis", "Missouri", "US"),
+        None
+    );
+}
+
+#[test]
+fn token_set_matching_ignores_word_order_only_when_enabled() {
+    let location_index = common::sample_index();
+    assert_eq!(
+        full_match_city(&location_index, "Francisco San", "California", "US"),
+        None
+    );
+    assert!(!matches!(
+        location_index
+            .find_location("Francisco San", "California", "US")
+            .unwrap(),
+        LocationMatchType::TokenSetMatch { .. }
+    ));
+
+    let location_index = common::sample_builder()
+        .token_set_match(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        location_index
+            .find_location("Francisco San", "California", "US")
+            .unwrap(),
+        LocationMatchType::TokenSetMatch {
+            city: CityId(111000),
+            state: StateId(1416),
+            country: CountryId(233),
+        }
+    );
+    // Abbreviations are expanded before the words are compared.
+    assert!(matches!(
+        location_index
+            .find_location("Louis St", "MO", "US")
+            .unwrap(),
+        LocationMatchType::TokenSetMatch {
+            city: CityId(111001),
+            ..
+        }
+    ));
+}
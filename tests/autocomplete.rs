@@ -0,0 +1,135 @@
+mod common;
+
+use location_finder::location_finder::LocationIndex;
+use location_finder::location_id::{CityId, CountryId, PlaceId, StateId};
+
+const EXTRA_CITIES_CSV: &str = "\
+28500,Bamberg,3009,BY,Bavaria,82,DE,Germany,49.89,10.89,Q3936
+28501,Bayreuth,3009,BY,Bavaria,82,DE,Germany,49.95,11.58,Q3923
+28502,Bad Homburg,3018,HE,Hesse,82,DE,Germany,50.23,8.62,Q14871
+28503,Deuerling,3009,BY,Bavaria,82,DE,Germany,49.03,11.91,Q504389
+";
+
+fn autocomplete_index(cities_csv: &str) -> LocationIndex {
+    common::sample_builder()
+        .cities(cities_csv.as_bytes())
+        .autocomplete(true)
+        .build()
+        .unwrap()
+}
+
+fn autocomplete_cities_csv() -> String {
+    format!("{}{}", common::CITIES_CSV, EXTRA_CITIES_CSV)
+}
+
+fn suggested_places(
+    location_index: &LocationIndex,
+    prefix: &str,
+    country: Option<&str>,
+    limit: usize,
+) -> Vec<PlaceId> {
+    location_index
+        .autocomplete(prefix, country, limit)
+        .into_iter()
+        .map(|suggestion| suggestion.place)
+        .collect()
+}
+
+#[test]
+fn capitals_come_before_other_cities() {
+    let cities_csv = autocomplete_cities_csv();
+    let location_index = autocomplete_index(&cities_csv);
+    assert_eq!(
+        suggested_places(&location_index, "B", None, 10),
+        vec![
+            // Berlin is Germany's capital, the other cities follow
+            // alphabetically.
+            PlaceId::City(CityId(28002)),
+            PlaceId::City(CityId(28502)),
+            PlaceId::City(CityId(28500)),
+            PlaceId::City(CityId(28501)),
+            PlaceId::State(StateId(3009)),
+            PlaceId::State(StateId(3010)),
+        ]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "B", None, 2),
+        vec![PlaceId::City(CityId(28002)), PlaceId::City(CityId(28502))]
+    );
+}
+
+#[test]
+fn own_names_come_before_alias_and_native_names() {
+    let cities_csv = autocomplete_cities_csv();
+    let location_index = autocomplete_index(&cities_csv);
+    // Germany only matches through its native name, Deutschland.
+    assert_eq!(
+        suggested_places(&location_index, "Deu", None, 10),
+        vec![
+            PlaceId::City(CityId(28503)),
+            PlaceId::Country(CountryId(82))
+        ]
+    );
+    // Countries rank before states among own names.
+    assert_eq!(
+        suggested_places(&location_index, "M", None, 10),
+        vec![
+            PlaceId::City(CityId(28000)),
+            PlaceId::Country(CountryId(142)),
+            PlaceId::State(StateId(1451)),
+        ]
+    );
+}
+
+#[test]
+fn equal_places_are_ordered_by_name_then_id() {
+    let location_index = autocomplete_index(common::CITIES_CSV);
+    assert_eq!(
+        suggested_places(&location_index, "San", None, 10),
+        vec![PlaceId::City(CityId(111000)), PlaceId::City(CityId(130000))]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "Neu", None, 10),
+        vec![PlaceId::City(CityId(28100)), PlaceId::City(CityId(28101))]
+    );
+}
+
+#[test]
+fn country_filter_limits_suggestions() {
+    let location_index = autocomplete_index(common::CITIES_CSV);
+    assert_eq!(
+        suggested_places(&location_index, "San", Some("MX"), 10),
+        vec![PlaceId::City(CityId(130000))]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "San", Some("usa"), 10),
+        vec![PlaceId::City(CityId(111000))]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "San", Some("United Kingdom"), 10),
+        vec![]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "San", Some("Atlantis"), 10),
+        vec![]
+    );
+}
+
+#[test]
+fn accents_do_not_matter() {
+    let location_index = autocomplete_index(common::CITIES_CSV);
+    assert_eq!(
+        suggested_places(&location_index, "Mün", None, 10),
+        vec![PlaceId::City(CityId(28000))]
+    );
+    assert_eq!(
+        suggested_places(&location_index, "Mün", None, 10),
+        suggested_places(&location_index, "Mun", None, 10)
+    );
+}
+
+#[test]
+fn autocomplete_is_off_by_default() {
+    let location_index = common::sample_index();
+    assert!(location_index.autocomplete("Mun", None, 10).is_empty());
+}
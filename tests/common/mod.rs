@@ -0,0 +1,118 @@
+#![allow(dead_code)]
+
+use location_finder::location_finder::{LocationIndex, LocationIndexBuilder};
+
+pub const COUNTRIES_CSV: &str = "\
+id,name,iso3,iso2,numeric_code,phone_code,capital,currency,currency_name,currency_symbol,tld,native,region,subregion,timezones,latitude,longitude,emoji,emojiU
+82,Germany,DEU,DE,276,49,Berlin,EUR,Euro,€,.de,Deutschland,Europe,Western Europe,\"[]\",51.0,9.0,🇩🇪,U+1F1E9 U+1F1EA
+233,United States,USA,US,840,1,Washington,USD,United States dollar,$,.us,United States,Americas,Northern America,\"[]\",38.0,-97.0,🇺🇸,U+1F1FA U+1F1F8
+232,United Kingdom,GBR,GB,826,44,London,GBP,British pound,£,.uk,United Kingdom,Europe,Northern Europe,\"[]\",54.0,-2.0,🇬🇧,U+1F1EC U+1F1E7
+142,Mexico,MEX,MX,484,52,Mexico City,MXN,Mexican peso,$,.mx,México,Americas,Central America,\"[]\",23.0,-102.0,🇲🇽,U+1F1F2 U+1F1FD
+";
+
+pub const STATES_CSV: &str = "\
+id,name,country_id,country_code,country_name,state_code,type,latitude,longitude
+3009,Bavaria,82,DE,Germany,BY,state,48.79,11.49
+3018,Hesse,82,DE,Germany,HE,state,50.65,9.16
+3010,Berlin,82,DE,Germany,BE,state,52.52,13.40
+1416,California,233,US,United States,CA,state,36.77,-119.41
+1451,Missouri,233,US,United States,MO,state,37.96,-91.83
+2336,England,232,GB,United Kingdom,ENG,country,52.35,-1.17
+3449,Jalisco,142,MX,Mexico,JAL,state,20.66,-103.35
+";
+
+pub const CITIES_CSV: &str = "\
+id,name,state_id,state_code,state_name,country_id,country_code,country_name,latitude,longitude,wikiDataId
+28000,Munich,3009,BY,Bavaria,82,DE,Germany,48.13743,11.57549,Q1726
+28001,Frankfurt am Main,3018,HE,Hesse,82,DE,Germany,50.11552,8.68417,Q1794
+28002,Berlin,3010,BE,Berlin,82,DE,Germany,52.52437,13.41053,Q64
+28100,Neustadt,3009,BY,Bavaria,82,DE,Germany,49.0,11.0,Q100
+28101,Neustadt,3018,HE,Hesse,82,DE,Germany,50.0,9.0,Q101
+111000,San Francisco,1416,CA,California,233,US,United States,37.77493,-122.41942,Q62
+111001,Saint Louis,1451,MO,Missouri,233,US,United States,38.62727,-90.19789,Q38022
+50000,London,2336,ENG,England,232,GB,United Kingdom,51.50853,-0.12574,Q84
+130000,San Francisco,3449,JAL,Jalisco,142,MX,Mexico,20.9,-102.4,Q3473
+";
+
+pub const PLACE_ALIAS_TXT: &str = "\
+Munich, Bavaria, Germany|München, Bayern, Germany
+";
+
+/// A builder over the sample dataset above.
+pub fn sample_builder<'a>() -> LocationIndexBuilder<'a> {
+    LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(CITIES_CSV.as_bytes())
+        .place_alias(PLACE_ALIAS_TXT.as_bytes())
+}
+
+pub fn sample_index() -> LocationIndex {
+    sample_builder().build().unwrap()
+}
+
+/// A small deterministic generator, so generated datasets are the same on
+/// every run.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg {
+        Lcg(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A value in `[low, high)`.
+    pub fn between(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * (self.next_u64() as f64 / (1u64 << 31) as f64)
+    }
+
+    /// A capitalized word of `min_len..=max_len` letters from `alphabet`.
+    pub fn word(&mut self, alphabet: &[u8], min_len: u64, max_len: u64) -> String {
+        let len = min_len + self.below(max_len - min_len + 1);
+        let mut word: String = (0..len)
+            .map(|_| alphabet[self.below(alphabet.len() as u64) as usize] as char)
+            .collect();
+        word[..1].make_ascii_uppercase();
+        word
+    }
+}
+
+/// A generated city in Bavaria, Germany.
+pub struct GeneratedCity {
+    pub id: u64,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// `cities.csv` rows for `cities`, after the header.
+pub fn generated_cities_csv(cities: &[GeneratedCity]) -> String {
+    let mut cities_csv = CITIES_CSV.lines().next().unwrap().to_string();
+    cities_csv.push('\n');
+    for city in cities {
+        cities_csv.push_str(&format!(
+            "{},{},3009,BY,Bavaria,82,DE,Germany,{},{},Q{}\n",
+            city.id, city.name, city.latitude, city.longitude, city.id
+        ));
+    }
+    cities_csv
+}
+
+/// A builder over the sample countries and states and the generated cities.
+pub fn generated_builder(cities_csv: &str) -> LocationIndexBuilder<'_> {
+    LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(cities_csv.as_bytes())
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::{CITIES_CSV, COUNTRIES_CSV, STATES_CSV};
+use location_finder::error::LocationFinderError;
+use location_finder::location_finder::LocationIndex;
+use location_finder::location_id::{CityId, CountryId, PlaceId, StateId};
+
+#[test]
+fn sample_dataset_loads() {
+    assert!(common::sample_builder().build().is_ok());
+}
+
+#[test]
+fn dangling_references_are_listed() {
+    let cities_csv = format!(
+        "{}90000,Nowhere,9999,XX,Nowhere,82,DE,Germany,50.0,10.0,Q1\n\
+         90001,Elsewhere,3009,BY,Bavaria,999,XX,Nowhere,50.0,10.0,Q2\n",
+        CITIES_CSV
+    );
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(cities_csv.as_bytes())
+        .build();
+    let Err(LocationFinderError::DanglingReferences(dangling_references)) = result else {
+        panic!("expected dangling references");
+    };
+    let found: Vec<(PlaceId, &str, PlaceId)> = dangling_references
+        .iter()
+        .map(|dangling_reference| {
+            (
+                dangling_reference.record_id,
+                dangling_reference.field,
+                dangling_reference.target_id,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                PlaceId::City(CityId(90000)),
+                "state_id",
+                PlaceId::State(StateId(9999))
+            ),
+            (
+                PlaceId::City(CityId(90001)),
+                "country_id",
+                PlaceId::Country(CountryId(999))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn invalid_rows_are_reported_with_their_line() {
+    let cities_csv = format!(
+        "{}90000,Bad State,notanumber,BY,Bavaria,82,DE,Germany,50.0,10.0,Q1\n\
+         90001,Truncated,3009\n",
+        CITIES_CSV
+    );
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(cities_csv.as_bytes())
+        .build();
+    let Err(LocationFinderError::InvalidRecords(invalid_records)) = result else {
+        panic!("expected invalid records");
+    };
+    let first_bad_line = CITIES_CSV.lines().count() as u64 + 1;
+    let lines: Vec<Option<u64>> = invalid_records
+        .iter()
+        .map(|invalid_record| invalid_record.location.line)
+        .collect();
+    assert_eq!(lines, vec![Some(first_bad_line), Some(first_bad_line + 1)]);
+    assert!(invalid_records
+        .iter()
+        .all(|invalid_record| invalid_record.location.source_name == "cities.csv"));
+}
+
+/// A source that fails on the first read, like a file on a dropped network
+/// share.
+struct FailingReader;
+
+impl std::io::Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("connection reset"))
+    }
+}
+
+#[test]
+fn header_errors_name_the_source() {
+    let mut states_csv = b"id,name,\xff".to_vec();
+    states_csv.extend_from_slice(&STATES_CSV.as_bytes()[STATES_CSV.find('\n').unwrap()..]);
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(states_csv.as_slice())
+        .cities(CITIES_CSV.as_bytes())
+        .build();
+    let Err(LocationFinderError::InvalidRecords(invalid_records)) = result else {
+        panic!("expected invalid records");
+    };
+    assert_eq!(invalid_records.len(), 1);
+    assert_eq!(invalid_records[0].location.source_name, "states.csv");
+    assert_eq!(invalid_records[0].location.line, Some(1));
+
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(FailingReader)
+        .build();
+    let Err(LocationFinderError::IO { location, source }) = result else {
+        panic!("expected an I/O error");
+    };
+    assert_eq!(location.source_name, "cities.csv");
+    assert_eq!(source.to_string(), "connection reset");
+}
+
+#[test]
+fn duplicate_ids_are_rejected() {
+    let cities_csv = format!(
+        "{}28000,Munich Again,3009,BY,Bavaria,82,DE,Germany,48.1,11.5,Q1726\n",
+        CITIES_CSV
+    );
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(cities_csv.as_bytes())
+        .build();
+    assert!(matches!(
+        result,
+        Err(LocationFinderError::DuplicateRecord {
+            id: PlaceId::City(CityId(28000)),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn dangling_references_name_the_record_and_target() {
+    let cities_csv = format!(
+        "{}90000,Nowhere,9999,XX,Nowhere,82,DE,Germany,50.0,10.0,Q1\n",
+        CITIES_CSV
+    );
+    let result = LocationIndex::builder()
+        .countries(COUNTRIES_CSV.as_bytes())
+        .states(STATES_CSV.as_bytes())
+        .cities(cities_csv.as_bytes())
+        .build();
+    let Err(LocationFinderError::DanglingReferences(dangling_references)) = result else {
+        panic!("expected dangling references");
+    };
+    assert_eq!(
+        dangling_references[0].to_string(),
+        format!(
+            "cities.csv:{}: city 90000 has state_id pointing to missing state 9999",
+            CITIES_CSV.lines().count() + 1
+        )
+    );
+}
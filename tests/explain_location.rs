@@ -0,0 +1,46 @@
+mod common;
+
+use location_finder::explain::{MatchRule, MatchStage};
+use location_finder::location_id::{CityId, PlaceId};
+use location_finder::location_query::LocationQuery;
+
+#[test]
+fn keys_and_stages_list_typed_ids() {
+    let location_index = common::sample_index();
+    let explanation = location_index
+        .explain_location(&LocationQuery::new().city("Neustadt").country("DE"))
+        .unwrap();
+    let city_country_lookup = explanation
+        .keys_tried
+        .iter()
+        .find(|key_lookup| key_lookup.stage == MatchStage::CityCountryKey)
+        .unwrap();
+    let neustadt = vec![PlaceId::City(CityId(28100)), PlaceId::City(CityId(28101))];
+    assert_eq!(city_country_lookup.ids, neustadt);
+    let city_country_stage = explanation
+        .stages
+        .iter()
+        .find(|stage_candidates| stage_candidates.stage == MatchStage::CityCountryKey)
+        .unwrap();
+    assert_eq!(city_country_stage.ids, neustadt);
+}
+
+#[test]
+fn hint_tie_break_lists_the_kept_places() {
+    let location_index = common::sample_index();
+    let explanation = location_index
+        .explain_location(
+            &LocationQuery::new()
+                .city("Neustadt")
+                .country("DE")
+                .bias_coordinate(50.1, 8.9),
+        )
+        .unwrap();
+    let hint_tie_break = explanation.rules.iter().find_map(|rule| match rule {
+        MatchRule::HintTieBreak { candidates, kept } => Some((candidates, kept)),
+        _ => None,
+    });
+    let (candidates, kept) = hint_tie_break.expect("expected a hint tie-break");
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(kept, &vec![PlaceId::City(CityId(28101))]);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{GeneratedCity, Lcg};
+use location_finder::location_finder::{
+    normalize_location_str, FuzzyMatchConfig, LocationMatchType,
+};
+use location_finder::location_id::CityId;
+use std::collections::BTreeSet;
+
+const ALPHABET: &[u8] = b"abcde";
+
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution_cost = usize::from(a[i - 1] != b[j - 1]);
+            distances[i][j] = (distances[i - 1][j - 1] + substitution_cost)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+fn generated_cities(lcg: &mut Lcg, count: usize) -> Vec<GeneratedCity> {
+    let mut names = BTreeSet::new();
+    while names.len() < count {
+        names.insert(lcg.word(ALPHABET, 4, 7));
+    }
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| GeneratedCity {
+            id: 200000 + index as u64,
+            name,
+            latitude: 48.0,
+            longitude: 11.0,
+        })
+        .collect()
+}
+
+fn matched_cities(location_match: &LocationMatchType) -> Vec<(CityId, usize)> {
+    match location_match {
+        LocationMatchType::FuzzyMatch { city, distance, .. } => vec![(*city, *distance)],
+        LocationMatchType::Ambiguous { candidates } => {
+            candidates.iter().flat_map(matched_cities).collect()
+        }
+        // Without a city match the lookup falls back to the country.
+        LocationMatchType::CountryMatch { .. } => vec![],
+        other => panic!("expected a fuzzy match, got {:?}", other),
+    }
+}
+
+#[test]
+fn fuzzy_matches_agree_with_brute_force() {
+    let mut lcg = Lcg::new(7);
+    let cities = generated_cities(&mut lcg, 400);
+    let cities_csv = common::generated_cities_csv(&cities);
+    let mut matched_query_count = 0;
+    for max_distance in [1, 2] {
+        let location_index = common::generated_builder(&cities_csv)
+            .fuzzy_match(FuzzyMatchConfig { max_distance })
+            .build()
+            .unwrap();
+        for _ in 0..200 {
+            let query = lcg.word(ALPHABET, 3, 8);
+            let normalized_query = normalize_location_str(&query);
+            let distances: Vec<(CityId, usize)> = cities
+                .iter()
+                .map(|city| {
+                    (
+                        CityId(city.id),
+                        levenshtein_distance(
+                            &normalized_query,
+                            &normalize_location_str(&city.name),
+                        ),
+                    )
+                })
+                .collect();
+            let min_distance = distances
+                .iter()
+                .map(|(_, distance)| *distance)
+                .min()
+                .unwrap();
+            if min_distance == 0 {
+                continue;
+            }
+            let mut expected: Vec<(CityId, usize)> = distances
+                .into_iter()
+                .filter(|(_, distance)| *distance == min_distance && *distance <= max_distance)
+                .collect();
+            expected.sort();
+            let mut found =
+                matched_cities(&location_index.find_location(&query, "", "DE").unwrap());
+            found.sort();
+            assert_eq!(found, expected, "query {:?}", query);
+            matched_query_count += usize::from(!found.is_empty());
+        }
+    }
+    assert!(matched_query_count > 100);
+}
+
+#[test]
+fn fuzzy_stage_is_off_by_default() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index.find_location("Munihc", "", "DE").unwrap(),
+        LocationMatchType::CountryMatch { .. }
+    ));
+}
+
+#[test]
+fn fuzzy_match_needs_a_country() {
+    let location_index = common::sample_builder()
+        .fuzzy_match(FuzzyMatchConfig::default())
+        .build()
+        .unwrap();
+    assert!(matches!(
+        location_index.find_location("Munihc", "", "DE").unwrap(),
+        LocationMatchType::FuzzyMatch {
+            city: CityId(28000),
+            distance: 2,
+            ..
+        }
+    ));
+    assert!(matches!(
+        location_index.find_location("Munihc", "", "").unwrap(),
+        LocationMatchType::NoMatch
+    ));
+}
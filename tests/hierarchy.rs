@@ -0,0 +1,107 @@
+mod common;
+
+use location_finder::location_id::{CityId, CountryId, StateId};
+use std::collections::BTreeSet;
+
+#[test]
+fn children_are_listed_in_id_order() {
+    let location_index = common::sample_index();
+    // The sample CSVs list Hesse before Berlin and London after San
+    // Francisco, so the order below does not come from the files.
+    let state_ids: Vec<StateId> = location_index
+        .states_of(CountryId(82))
+        .map(|state_record| state_record.id)
+        .collect();
+    assert_eq!(state_ids, vec![StateId(3009), StateId(3010), StateId(3018)]);
+
+    let city_ids: Vec<CityId> = location_index
+        .cities_of(StateId(3009))
+        .map(|city_record| city_record.id)
+        .collect();
+    assert_eq!(city_ids, vec![CityId(28000), CityId(28100)]);
+
+    let city_ids: Vec<CityId> = location_index
+        .cities_of_country(CountryId(82))
+        .map(|city_record| city_record.id)
+        .collect();
+    assert_eq!(
+        city_ids,
+        vec![
+            CityId(28000),
+            CityId(28001),
+            CityId(28002),
+            CityId(28100),
+            CityId(28101)
+        ]
+    );
+
+    let city_ids: Vec<CityId> = location_index
+        .cities_of_country(CountryId(233))
+        .map(|city_record| city_record.id)
+        .collect();
+    assert_eq!(city_ids, vec![CityId(111000), CityId(111001)]);
+}
+
+#[test]
+fn unknown_ids_have_no_children() {
+    let location_index = common::sample_index();
+    assert_eq!(location_index.states_of(CountryId(999)).count(), 0);
+    assert_eq!(location_index.cities_of(StateId(999)).count(), 0);
+    assert_eq!(location_index.cities_of_country(CountryId(999)).count(), 0);
+    // A city ID is not a state ID.
+    assert_eq!(location_index.cities_of(StateId(28000)).count(), 0);
+}
+
+#[test]
+fn parent_state_follows_the_city_record() {
+    let location_index = common::sample_index();
+    let parent_state = |city_id| {
+        location_index
+            .parent_state(city_id)
+            .map(|state_record| state_record.id)
+    };
+    assert_eq!(parent_state(CityId(28000)), Some(StateId(3009)));
+    assert_eq!(parent_state(CityId(28101)), Some(StateId(3018)));
+    assert_eq!(parent_state(CityId(130000)), Some(StateId(3449)));
+    assert_eq!(parent_state(CityId(999)), None);
+}
+
+#[test]
+fn iterators_cover_every_record_once() {
+    let location_index = common::sample_index();
+    let country_ids: BTreeSet<CountryId> = location_index
+        .countries()
+        .map(|country_record| country_record.id)
+        .collect();
+    assert_eq!(
+        country_ids,
+        BTreeSet::from([
+            CountryId(82),
+            CountryId(142),
+            CountryId(232),
+            CountryId(233)
+        ])
+    );
+    assert_eq!(location_index.countries().count(), country_ids.len());
+
+    let state_ids: BTreeSet<StateId> = location_index
+        .states()
+        .map(|state_record| state_record.id)
+        .collect();
+    assert_eq!(state_ids.len(), 7);
+    assert_eq!(location_index.states().count(), state_ids.len());
+
+    let city_ids: BTreeSet<CityId> = location_index
+        .cities()
+        .map(|city_record| city_record.id)
+        .collect();
+    assert_eq!(city_ids.len(), 9);
+    assert_eq!(location_index.cities().count(), city_ids.len());
+    // Every city is reachable through its country.
+    for country_id in country_ids {
+        for city_record in location_index.cities_of_country(country_id) {
+            assert!(city_ids.contains(&city_record.id));
+            assert_eq!(city_record.country_id, country_id);
+        }
+    }
+}
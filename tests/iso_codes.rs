@@ -0,0 +1,112 @@
+mod common;
+
+use location_finder::explain::MatchRule;
+use location_finder::location_finder::LocationMatchType;
+use location_finder::location_id::{CityId, CountryId, StateId};
+use location_finder::location_query::LocationQuery;
+
+#[test]
+fn states_by_iso3166_2_code() {
+    let location_index = common::sample_index();
+    for code in ["DE-BY", "de-by", " DE-BY "] {
+        let state_record = location_index.find_state_by_iso3166_2(code).unwrap();
+        assert_eq!(state_record.id, StateId(3009));
+    }
+    assert_eq!(
+        location_index.find_state_by_iso3166_2("US-CA").unwrap().id,
+        StateId(1416)
+    );
+    for code in ["DE-CA", "DE-", "DEBY", "XX-BY", ""] {
+        assert!(location_index.find_state_by_iso3166_2(code).is_none());
+    }
+}
+
+#[test]
+fn countries_by_code() {
+    let location_index = common::sample_index();
+    assert_eq!(
+        location_index.find_country_by_numeric(276).unwrap().id,
+        CountryId(82)
+    );
+    assert!(location_index.find_country_by_numeric(999).is_none());
+    assert_eq!(
+        location_index.find_country_by_iso2("de").unwrap().id,
+        CountryId(82)
+    );
+    assert_eq!(
+        location_index.find_country_by_iso3("DEU").unwrap().id,
+        CountryId(82)
+    );
+    assert!(location_index.find_country_by_iso2("DEU").is_none());
+    assert!(location_index.find_country_by_iso3("DE").is_none());
+}
+
+#[test]
+fn subdivision_code_as_state_input() {
+    let location_index = common::sample_index();
+    for country in ["DE", ""] {
+        assert!(matches!(
+            location_index
+                .find_location("Munich", "DE-BY", country)
+                .unwrap(),
+            LocationMatchType::FullMatch {
+                city: CityId(28000),
+                ..
+            }
+        ));
+    }
+    // A subdivision of another country is not read as a code.
+    assert!(!matches!(
+        location_index
+            .find_location("Munich", "US-CA", "DE")
+            .unwrap(),
+        LocationMatchType::FullMatch { .. }
+    ));
+}
+
+#[test]
+fn numeric_code_as_country_input() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index
+            .find_location("Munich", "Bavaria", "276")
+            .unwrap(),
+        LocationMatchType::FullMatch {
+            city: CityId(28000),
+            ..
+        }
+    ));
+    assert!(matches!(
+        location_index.find_location("", "", "276").unwrap(),
+        LocationMatchType::CountryMatch {
+            country: CountryId(82)
+        }
+    ));
+}
+
+#[test]
+fn code_rules_are_explained() {
+    let location_index = common::sample_index();
+    let explanation = location_index
+        .explain_location(
+            &LocationQuery::new()
+                .city("Munich")
+                .state("DE-BY")
+                .country("276"),
+        )
+        .unwrap();
+    assert!(explanation.rules.iter().any(|rule| matches!(
+        rule,
+        MatchRule::NumericCountryCode {
+            code: 276,
+            country: CountryId(82)
+        }
+    )));
+    assert!(explanation.rules.iter().any(|rule| matches!(
+        rule,
+        MatchRule::SubdivisionCode {
+            state: StateId(3009),
+            ..
+        }
+    )));
+}
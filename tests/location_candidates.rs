@@ -0,0 +1,91 @@
+mod common;
+
+use location_finder::location_finder::{LocationMatchType, MatchReason};
+use location_finder::location_id::{CityId, CountryId};
+
+const QUERIES: &[(&str, &str, &str)] = &[
+    ("Munich", "Bavaria", "Germany"),
+    ("München", "Bayern", "Germany"),
+    ("Munich", "", "DE"),
+    ("Munich", "Hesse", "DE"),
+    ("Neustadt", "", "DE"),
+    ("San Francisco", "CA", "USA"),
+    ("", "Bavaria", "DE"),
+    ("", "", "United Kingdom"),
+    ("Atlantis", "", ""),
+];
+
+#[test]
+fn best_candidate_agrees_with_find_location() {
+    let location_index = common::sample_index();
+    for (city, state, country) in QUERIES {
+        let location_match = location_index.find_location(city, state, country).unwrap();
+        let candidates = location_index.find_location_candidates(city, state, country, 10);
+        match location_match {
+            LocationMatchType::NoMatch => assert!(candidates.is_empty()),
+            LocationMatchType::Ambiguous { candidates: tied } => {
+                for (candidate, tied) in candidates.iter().zip(&tied) {
+                    assert_eq!(&candidate.location, tied);
+                }
+            }
+            location_match => assert_eq!(candidates[0].location, location_match),
+        }
+    }
+}
+
+#[test]
+fn ambiguous_candidates_share_the_score() {
+    let location_index = common::sample_index();
+    let candidates = location_index.find_location_candidates("Neustadt", "", "DE", 10);
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0].score, candidates[1].score);
+    assert!(candidates[0].score < 1.0);
+    assert_eq!(
+        candidates[0].reasons,
+        vec![MatchReason::StateMissing, MatchReason::IsoCountryCode]
+    );
+}
+
+#[test]
+fn alias_matches_carry_the_place_alias_reason() {
+    let location_index = common::sample_index();
+    let candidates = location_index.find_location_candidates("München", "Bayern", "Germany", 10);
+    assert!(matches!(
+        candidates[0].location,
+        LocationMatchType::FullMatch {
+            city: CityId(28000),
+            ..
+        }
+    ));
+    assert!(candidates[0].reasons.contains(&MatchReason::PlaceAlias));
+}
+
+#[test]
+fn country_only_input_yields_a_country_candidate() {
+    let location_index = common::sample_index();
+    let candidates = location_index.find_location_candidates("", "", "GB", 10);
+    assert_eq!(candidates.len(), 1);
+    assert!(matches!(
+        candidates[0].location,
+        LocationMatchType::CountryMatch {
+            country: CountryId(232)
+        }
+    ));
+}
+
+#[test]
+fn place_alias_is_only_reported_for_alias_keys() {
+    let location_index = common::sample_index();
+    let LocationMatchType::FullMatch { provenance, .. } = location_index
+        .find_location("München", "Bayern", "Germany")
+        .unwrap()
+    else {
+        panic!("expected a full match");
+    };
+    assert_eq!(
+        provenance.place_alias.map(|place_alias| place_alias.name),
+        Some("München, Bayern, Germany".to_string())
+    );
+    let candidates = location_index.find_location_candidates("Munich", "Bavaria", "Germany", 10);
+    assert!(!candidates[0].reasons.contains(&MatchReason::PlaceAlias));
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use location_finder::location_finder::LocationMatchType;
+use location_finder::location_id::CityId;
+use location_finder::location_parser::LocationInterpretation;
+
+fn parse(location_str: &str) -> LocationInterpretation {
+    common::sample_index()
+        .find_location_str(location_str)
+        .unwrap_or_else(|| panic!("no interpretation for {:?}", location_str))
+}
+
+fn matched_city(interpretation: &LocationInterpretation) -> CityId {
+    match interpretation.location {
+        LocationMatchType::FullMatch { city, .. } => city,
+        ref other => panic!("expected a full match, got {:?}", other),
+    }
+}
+
+fn levels(interpretation: &LocationInterpretation) -> [Option<&str>; 3] {
+    [
+        interpretation.city.as_deref(),
+        interpretation.state.as_deref(),
+        interpretation.country.as_deref(),
+    ]
+}
+
+#[test]
+fn comma_separated_levels() {
+    let interpretation = parse("Munich, Bavaria, DE");
+    assert_eq!(matched_city(&interpretation), CityId(28000));
+    assert_eq!(
+        levels(&interpretation),
+        [Some("Munich"), Some("Bavaria"), Some("DE")]
+    );
+    assert_eq!(interpretation.score, 1.0);
+}
+
+#[test]
+fn city_and_country_without_separator() {
+    let interpretation = parse("Berlin Germany");
+    assert_eq!(matched_city(&interpretation), CityId(28002));
+    assert_eq!(
+        levels(&interpretation),
+        [Some("Berlin"), None, Some("Germany")]
+    );
+}
+
+#[test]
+fn country_in_parentheses() {
+    let interpretation = parse("London (GB)");
+    assert_eq!(matched_city(&interpretation), CityId(50000));
+    assert_eq!(levels(&interpretation), [Some("London"), None, Some("GB")]);
+    // "UK" is not a country name or code in the dataset, so it is dropped
+    // rather than read as a state that the GB policy would promote.
+    let interpretation = parse("London (UK)");
+    assert_eq!(matched_city(&interpretation), CityId(50000));
+    assert_eq!(levels(&interpretation), [Some("London"), None, None]);
+    assert_eq!(interpretation.ignored.as_deref(), Some("UK"));
+}
+
+#[test]
+fn city_alone() {
+    let interpretation = parse("Berlin");
+    assert_eq!(matched_city(&interpretation), CityId(28002));
+    assert_eq!(levels(&interpretation), [Some("Berlin"), None, None]);
+}
+
+#[test]
+fn city_and_state_without_country() {
+    let interpretation = parse("Munich, Bavaria");
+    assert_eq!(matched_city(&interpretation), CityId(28000));
+    assert_eq!(
+        levels(&interpretation),
+        [Some("Munich"), Some("Bavaria"), None]
+    );
+}
+
+#[test]
+fn trailing_words_are_ignored_when_nothing_else_matches() {
+    let interpretation = parse("San Francisco Bay Area");
+    assert_eq!(matched_city(&interpretation), CityId(111000));
+    assert_eq!(levels(&interpretation), [Some("San Francisco"), None, None]);
+    assert_eq!(interpretation.ignored.as_deref(), Some("Bay Area"));
+    assert!(interpretation.score < parse("San Francisco").score);
+}
+
+#[test]
+fn a_full_match_after_dropping_words_beats_a_state_mismatch() {
+    // "Bay Area" reads as a mismatched state of San Francisco, Mexico.
+    let interpretation = parse("San Francisco Bay Area");
+    assert_eq!(matched_city(&interpretation), CityId(111000));
+    let interpretation = parse("Munich, Bavaria, Germany, Europe");
+    assert_eq!(matched_city(&interpretation), CityId(28000));
+    assert_eq!(
+        levels(&interpretation),
+        [Some("Munich"), Some("Bavaria"), Some("Germany")]
+    );
+    assert_eq!(interpretation.ignored.as_deref(), Some("Europe"));
+    assert_eq!(
+        interpretation.score,
+        0.9 * parse("Munich, Bavaria, Germany").score
+    );
+}
+
+#[test]
+fn unknown_places_are_not_interpreted() {
+    assert!(common::sample_index()
+        .find_location_str("Atlantis")
+        .is_none());
+}
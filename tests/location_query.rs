@@ -0,0 +1,98 @@
+mod common;
+
+use location_finder::location_finder::LocationMatchType;
+use location_finder::location_id::CityId;
+use location_finder::location_query::LocationQuery;
+
+/// The cities of a match, in order, whether it is ambiguous or not.
+fn matched_cities(location_match: &LocationMatchType) -> Vec<CityId> {
+    match location_match {
+        LocationMatchType::Ambiguous { candidates } => {
+            candidates.iter().flat_map(matched_cities).collect()
+        }
+        LocationMatchType::FullMatch { city, .. }
+        | LocationMatchType::SimilarStateMatch { city, .. }
+        | LocationMatchType::PartialMatch { city, .. }
+        | LocationMatchType::TokenSetMatch { city, .. }
+        | LocationMatchType::FuzzyMatch { city, .. } => vec![*city],
+        _ => vec![],
+    }
+}
+
+fn candidate_cities(query: &LocationQuery) -> Vec<CityId> {
+    common::sample_index()
+        .find_location_query_candidates(query, 10)
+        .iter()
+        .flat_map(|candidate| matched_cities(&candidate.location))
+        .collect()
+}
+
+#[test]
+fn default_country_only_applies_without_a_country() {
+    let location_index = common::sample_index();
+    let find_cities =
+        |query: LocationQuery| matched_cities(&location_index.find_location_query(&query).unwrap());
+    let san_francisco = || LocationQuery::new().city("San Francisco");
+    assert_eq!(
+        find_cities(san_francisco()),
+        vec![CityId(111000), CityId(130000)]
+    );
+    assert_eq!(
+        find_cities(san_francisco().default_country("US")),
+        vec![CityId(111000)]
+    );
+    assert_eq!(
+        find_cities(san_francisco().country("MX").default_country("US")),
+        vec![CityId(130000)]
+    );
+    // An empty country is still a given country, so the default is ignored.
+    assert_eq!(
+        find_cities(san_francisco().country("").default_country("US")),
+        vec![CityId(111000), CityId(130000)]
+    );
+
+    assert_eq!(
+        candidate_cities(&san_francisco().default_country("US")),
+        vec![CityId(111000)]
+    );
+    assert_eq!(
+        candidate_cities(&san_francisco().country("MX").default_country("US")),
+        vec![CityId(130000)]
+    );
+}
+
+#[test]
+fn preferred_countries_narrow_an_ambiguous_match() {
+    let location_index = common::sample_index();
+    let find_cities =
+        |query: LocationQuery| matched_cities(&location_index.find_location_query(&query).unwrap());
+    let san_francisco = || LocationQuery::new().city("San Francisco");
+    assert_eq!(
+        find_cities(san_francisco().preferred_countries(["MX"])),
+        vec![CityId(130000)]
+    );
+    // Countries without a candidate are skipped.
+    assert_eq!(
+        find_cities(san_francisco().preferred_countries(["GB", "United States", "MX"])),
+        vec![CityId(111000)]
+    );
+    assert_eq!(
+        find_cities(san_francisco().preferred_countries(["GB"])),
+        vec![CityId(111000), CityId(130000)]
+    );
+    // A preferred country never overrides the given one.
+    assert_eq!(
+        find_cities(san_francisco().country("US").preferred_countries(["MX"])),
+        vec![CityId(111000)]
+    );
+
+    // Candidates keep both cities, with the preferred country first.
+    assert_eq!(
+        candidate_cities(&san_francisco().preferred_countries(["MX"])),
+        vec![CityId(130000), CityId(111000)]
+    );
+    assert_eq!(
+        candidate_cities(&san_francisco().preferred_countries(["US"])),
+        vec![CityId(111000), CityId(130000)]
+    );
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use location_finder::location_finder::{
+    FuzzyMatchConfig, LocationCity, LocationCountry, LocationIndex, LocationMatchType,
+};
+use location_finder::location_id::{CityId, CountryId, StateId};
+
+// Saarland and its city have no coordinates, and neither does the extra
+// Bavarian city, so their matches fall back to the state or the country.
+const EXTRA_STATES_CSV: &str = "\
+3011,Saarland,82,DE,Germany,SL,state,,
+";
+
+const EXTRA_CITIES_CSV: &str = "\
+28400,Saarbrücken,3011,SL,Saarland,82,DE,Germany,,,Q1724
+28401,Erding,3009,BY,Bavaria,82,DE,Germany,,,
+";
+
+fn matched_location_index(states_csv: &str, cities_csv: &str) -> LocationIndex {
+    common::sample_builder()
+        .states(states_csv.as_bytes())
+        .cities(cities_csv.as_bytes())
+        .token_set_match(true)
+        .fuzzy_match(FuzzyMatchConfig::default())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn records_round_trip_through_json_with_their_renames() {
+    let location_index = common::sample_index();
+    let city_record = location_index.get_city_by_id(CityId(28000)).unwrap();
+    let json = serde_json::to_value(city_record).unwrap();
+    assert_eq!(json["wikiDataId"], "Q1726");
+    assert!(json.get("wiki_data_id").is_none());
+    let round_trip: LocationCity = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(round_trip.wiki_data_id, "Q1726");
+    assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+
+    let country_record = location_index.get_country_by_id(CountryId(82)).unwrap();
+    let json = serde_json::to_value(country_record).unwrap();
+    assert_eq!(json["emojiU"], "U+1F1E9 U+1F1EA");
+    assert!(json.get("emoji_u").is_none());
+    let round_trip: LocationCountry = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(round_trip.emoji_u, "U+1F1E9 U+1F1EA");
+    assert_eq!(serde_json::to_value(&round_trip).unwrap(), json);
+}
+
+#[test]
+fn every_city_level_match_resolves_to_its_records() {
+    let states_csv = format!("{}{}", common::STATES_CSV, EXTRA_STATES_CSV);
+    let cities_csv = format!("{}{}", common::CITIES_CSV, EXTRA_CITIES_CSV);
+    let location_index = matched_location_index(&states_csv, &cities_csv);
+    let queries = [
+        ("Munich", "Bavaria", "DE"),
+        ("Frankfurt am Main", "Hessen", "DE"),
+        ("Munich", "Hesse", "DE"),
+        ("Francisco San", "California", "US"),
+        ("Munihc", "", "DE"),
+    ];
+    let location_matches: Vec<LocationMatchType> = queries
+        .iter()
+        .map(|(city, state, country)| location_index.find_location(city, state, country).unwrap())
+        .collect();
+    assert!(matches!(
+        location_matches[0],
+        LocationMatchType::FullMatch { .. }
+    ));
+    assert!(matches!(
+        location_matches[1],
+        LocationMatchType::SimilarStateMatch { .. }
+    ));
+    assert!(matches!(
+        location_matches[2],
+        LocationMatchType::PartialMatch { .. }
+    ));
+    assert!(matches!(
+        location_matches[3],
+        LocationMatchType::TokenSetMatch { .. }
+    ));
+    assert!(matches!(
+        location_matches[4],
+        LocationMatchType::FuzzyMatch { .. }
+    ));
+
+    let expected = [
+        (28000, 3009, "DE", "BY", "Q1726", (48.13743, 11.57549)),
+        (28001, 3018, "DE", "HE", "Q1794", (50.11552, 8.68417)),
+        // A partial match reports the city's own state.
+        (28000, 3009, "DE", "BY", "Q1726", (48.13743, 11.57549)),
+        (111000, 1416, "US", "CA", "Q62", (37.77493, -122.41942)),
+        (28000, 3009, "DE", "BY", "Q1726", (48.13743, 11.57549)),
+    ];
+    for (location_match, (city_id, state_id, iso2, state_code, wikidata_id, coordinates)) in
+        location_matches.iter().zip(expected)
+    {
+        let matched_location = location_index.matched_location(location_match).unwrap();
+        assert_eq!(matched_location.city.unwrap().id, CityId(city_id));
+        assert_eq!(matched_location.state.unwrap().id, StateId(state_id));
+        assert_eq!(matched_location.country_code_iso2, iso2);
+        assert_eq!(matched_location.state_code.as_deref(), Some(state_code));
+        assert_eq!(matched_location.wikidata_id.as_deref(), Some(wikidata_id));
+        assert_eq!(
+            (matched_location.latitude, matched_location.longitude),
+            coordinates
+        );
+        assert_eq!(&matched_location.location_match, location_match);
+    }
+}
+
+#[test]
+fn state_and_country_matches_stop_at_their_level() {
+    let location_index = common::sample_index();
+    let location_match = location_index.find_location("", "Bavaria", "DE").unwrap();
+    assert!(matches!(
+        location_match,
+        LocationMatchType::StateMatch { .. }
+    ));
+    let matched_location = location_index.matched_location(&location_match).unwrap();
+    assert!(matched_location.city.is_none());
+    assert_eq!(matched_location.state.unwrap().id, StateId(3009));
+    assert_eq!(matched_location.state_code.as_deref(), Some("BY"));
+    assert_eq!(matched_location.wikidata_id, None);
+    assert_eq!(
+        (matched_location.latitude, matched_location.longitude),
+        (48.79, 11.49)
+    );
+
+    let location_match = location_index.find_location("", "", "GB").unwrap();
+    assert!(matches!(
+        location_match,
+        LocationMatchType::CountryMatch { .. }
+    ));
+    let matched_location = location_index.matched_location(&location_match).unwrap();
+    assert!(matched_location.city.is_none());
+    assert!(matched_location.state.is_none());
+    assert_eq!(matched_location.country.id, CountryId(232));
+    assert_eq!(matched_location.country_code_iso3, "GBR");
+    assert_eq!(matched_location.state_code, None);
+    assert_eq!(
+        (matched_location.latitude, matched_location.longitude),
+        (54.0, -2.0)
+    );
+}
+
+#[test]
+fn coordinates_fall_back_from_city_to_state_to_country() {
+    let states_csv = format!("{}{}", common::STATES_CSV, EXTRA_STATES_CSV);
+    let cities_csv = format!("{}{}", common::CITIES_CSV, EXTRA_CITIES_CSV);
+    let location_index = matched_location_index(&states_csv, &cities_csv);
+    let coordinates = |city: &str, state: &str| {
+        let location_match = location_index.find_location(city, state, "DE").unwrap();
+        let matched_location = location_index.matched_location(&location_match).unwrap();
+        (matched_location.latitude, matched_location.longitude)
+    };
+    assert_eq!(coordinates("Munich", "Bavaria"), (48.13743, 11.57549));
+    assert_eq!(coordinates("Erding", "Bavaria"), (48.79, 11.49));
+    assert_eq!(coordinates("Saarbrücken", "Saarland"), (51.0, 9.0));
+    assert_eq!(coordinates("", "Saarland"), (51.0, 9.0));
+}
+
+#[test]
+fn unresolved_matches_have_no_records() {
+    let location_index = common::sample_index();
+    let location_match = location_index.find_location("Neustadt", "", "DE").unwrap();
+    assert!(matches!(
+        location_match,
+        LocationMatchType::Ambiguous { .. }
+    ));
+    assert!(location_index.matched_location(&location_match).is_none());
+    let matched_location = location_index
+        .matched_location(&location_match.resolve())
+        .unwrap();
+    assert_eq!(matched_location.city.unwrap().id, CityId(28100));
+    assert!(location_index
+        .matched_location(&LocationMatchType::NoMatch)
+        .is_none());
+}
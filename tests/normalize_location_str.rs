@@ -0,0 +1,130 @@
+mod common;
+
+use location_finder::location_finder::{normalize_location_str, LocationMatchType};
+use location_finder::location_id::CityId;
+
+/// European place names in their native spelling next to the ASCII spelling
+/// users commonly type instead.
+const EUROPEAN_PLACE_NAMES: &[(&str, &str)] = &[
+    ("Łódź", "Lodz"),
+    ("Białystok", "Bialystok"),
+    ("Wrocław", "Wroclaw"),
+    ("Gdańsk", "Gdansk"),
+    ("Kraków", "Krakow"),
+    ("Tromsø", "Tromso"),
+    ("Ålesund", "Alesund"),
+    ("Bærum", "Baerum"),
+    ("København", "Kobenhavn"),
+    ("Sønderborg", "Sonderborg"),
+    ("Ærøskøbing", "Aeroskobing"),
+    ("Gießen", "Giessen"),
+    ("Düsseldorf", "Dusseldorf"),
+    ("Zürich", "Zurich"),
+    ("Þórshöfn", "Thorshofn"),
+    ("Hafnarfjörður", "Hafnarfjordur"),
+    ("Reykjavík", "Reykjavik"),
+    ("Đakovo", "Dakovo"),
+    ("Čakovec", "Cakovec"),
+    ("Şanlıurfa", "Sanliurfa"),
+    ("Ħamrun", "Hamrun"),
+    ("Málaga", "Malaga"),
+    ("Nykøbing Falster", "Nykobing Falster"),
+    ("ŁÓDŹ", "LODZ"),
+];
+
+#[test]
+fn native_and_ascii_spellings_share_a_key() {
+    for (native, ascii) in EUROPEAN_PLACE_NAMES {
+        assert_eq!(
+            normalize_location_str(native),
+            normalize_location_str(ascii),
+            "{} and {} should normalize to the same key",
+            native,
+            ascii
+        );
+    }
+}
+
+#[test]
+fn folded_letters_are_not_dropped() {
+    assert_eq!(normalize_location_str("Łódź"), "lodz");
+    assert_eq!(normalize_location_str("Tromsø"), "tromso");
+    assert_eq!(normalize_location_str("Gießen"), "giessen");
+}
+
+#[test]
+fn hyphens_dots_and_apostrophes_separate_words() {
+    assert_eq!(
+        normalize_location_str("Pays-de-la-Loire"),
+        normalize_location_str("Pays de la Loire")
+    );
+    assert_eq!(
+        normalize_location_str("Provence-Alpes-Côte-d’Azur"),
+        normalize_location_str("Provence-Alpes-Cote d'Azur")
+    );
+    assert_eq!(normalize_location_str("St.Louis"), "st_louis");
+}
+
+/// Place names in non-Latin scripts, one per script the transliteration
+/// covers.
+const NON_LATIN_PLACE_NAMES: &[&str] = &[
+    "Москва",
+    "Αθήνα",
+    "القاهرة",
+    "תל אביב",
+    "東京",
+    "北京",
+    "दिल्ली",
+];
+
+#[test]
+fn non_latin_names_normalize_to_non_empty_keys() {
+    for place_name in NON_LATIN_PLACE_NAMES {
+        let normalized = normalize_location_str(place_name);
+        assert!(
+            !normalized.is_empty(),
+            "{} normalized to an empty key",
+            place_name
+        );
+        assert!(
+            normalized.is_ascii(),
+            "{} normalized to {}",
+            place_name,
+            normalized
+        );
+    }
+}
+
+#[test]
+fn non_latin_names_do_not_collide() {
+    let mut keys: Vec<String> = NON_LATIN_PLACE_NAMES
+        .iter()
+        .map(|place_name| normalize_location_str(place_name))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), NON_LATIN_PLACE_NAMES.len());
+}
+
+#[test]
+fn cyrillic_transliterates_to_the_latin_spelling() {
+    assert_eq!(normalize_location_str("Москва"), "moskva");
+    assert_eq!(
+        normalize_location_str("Санкт-Петербург"),
+        normalize_location_str("Sankt Peterburg")
+    );
+}
+
+#[test]
+fn native_country_names_are_indexed() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index
+            .find_location("Munich", "Bavaria", "Deutschland")
+            .unwrap(),
+        LocationMatchType::FullMatch {
+            city: CityId(28000),
+            ..
+        }
+    ));
+}
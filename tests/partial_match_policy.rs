@@ -0,0 +1,109 @@
+mod common;
+
+use location_finder::error::LocationFinderError;
+use location_finder::location_finder::{LocationMatchType, MatchOrigin};
+use location_finder::partial_match_policy::{PartialMatchPolicies, PartialMatchPolicy};
+
+#[test]
+fn default_policies() {
+    let partial_match_policies = PartialMatchPolicies::default();
+    assert_eq!(partial_match_policies.get("US"), PartialMatchPolicy::Skip);
+    assert_eq!(
+        partial_match_policies.get("gb"),
+        PartialMatchPolicy::Override
+    );
+    assert_eq!(
+        partial_match_policies.get("DE"),
+        PartialMatchPolicy::StateSimilarity
+    );
+}
+
+#[test]
+fn policy_names() {
+    for (name, policy) in [
+        ("skip", PartialMatchPolicy::Skip),
+        ("override", PartialMatchPolicy::Override),
+        ("state_similarity", PartialMatchPolicy::StateSimilarity),
+        ("require_exact_state", PartialMatchPolicy::RequireExactState),
+    ] {
+        assert_eq!(PartialMatchPolicy::from_name(name), Some(policy));
+    }
+    assert_eq!(PartialMatchPolicy::from_name("Skip"), None);
+}
+
+#[test]
+fn policy_file_entries_apply_to_matching() {
+    let location_index = common::sample_builder()
+        .partial_match_policy(
+            "# comment\n\
+             \n\
+             de | override\n"
+                .as_bytes(),
+        )
+        .build()
+        .unwrap();
+    let location_match = location_index
+        .find_location("Munich", "Hesse", "DE")
+        .unwrap();
+    assert!(matches!(
+        location_match,
+        LocationMatchType::FullMatch { ref provenance, .. }
+            if provenance.origin == MatchOrigin::CountryOverride
+    ));
+}
+
+#[test]
+fn wildcard_sets_the_fallback_policy() {
+    let location_index = common::sample_builder()
+        .partial_match_policy("*|require_exact_state\n".as_bytes())
+        .build()
+        .unwrap();
+    assert!(matches!(
+        location_index
+            .find_location("Munich", "Bayern Land", "DE")
+            .unwrap(),
+        LocationMatchType::PartialMatch { .. }
+    ));
+    // Entries for other countries are kept.
+    assert!(matches!(
+        location_index
+            .find_location("London", "Kent", "GB")
+            .unwrap(),
+        LocationMatchType::FullMatch { .. }
+    ));
+}
+
+#[test]
+fn invalid_lines_are_reported_with_their_line() {
+    for (policy_file, line_number, line) in [
+        ("US|skip\nDE|sometimes\n", 2, "DE|sometimes"),
+        ("# comment\nDE\n", 2, "DE"),
+        ("|skip\n", 1, "|skip"),
+        ("DE|skip|override\n", 1, "DE|skip|override"),
+    ] {
+        let result = common::sample_builder()
+            .partial_match_policy(policy_file.as_bytes())
+            .build();
+        let Err(LocationFinderError::InvalidPartialMatchPolicy {
+            location,
+            line: invalid_line,
+        }) = result
+        else {
+            panic!("expected an invalid policy error for {:?}", policy_file);
+        };
+        assert_eq!(location.source_name, "partial_match_policy.txt");
+        assert_eq!(location.line, Some(line_number));
+        assert_eq!(invalid_line, line);
+    }
+}
+
+#[test]
+fn policy_file_is_optional() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index
+            .find_location("Munich", "Hesse", "DE")
+            .unwrap(),
+        LocationMatchType::PartialMatch { .. }
+    ));
+}
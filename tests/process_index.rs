@@ -0,0 +1,36 @@
+mod common;
+
+use common::{CITIES_CSV, COUNTRIES_CSV, STATES_CSV};
+use location_finder::location_finder::{
+    find_location, set_location_dataset_dir, set_partial_match_policy_file, try_init,
+    LocationMatchType, MatchOrigin,
+};
+use std::fs;
+
+// The process-wide index can only be loaded once, so this binary has a
+// single test.
+#[test]
+fn process_index_uses_the_configured_policy_file() {
+    let dataset_dir = std::env::temp_dir().join(format!(
+        "location_finder_process_index_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dataset_dir).unwrap();
+    fs::write(dataset_dir.join("countries.csv"), COUNTRIES_CSV).unwrap();
+    fs::write(dataset_dir.join("states.csv"), STATES_CSV).unwrap();
+    fs::write(dataset_dir.join("cities.csv"), CITIES_CSV).unwrap();
+    let policy_filename = dataset_dir.join("partial_match_policy.txt");
+    fs::write(&policy_filename, "DE|override\n").unwrap();
+
+    set_location_dataset_dir(Some(dataset_dir.to_str().unwrap().to_string()));
+    set_partial_match_policy_file(Some(policy_filename.to_str().unwrap().to_string()));
+    let result = try_init();
+    fs::remove_dir_all(&dataset_dir).unwrap();
+    result.unwrap();
+
+    assert!(matches!(
+        find_location("Munich", "Hesse", "DE").unwrap(),
+        LocationMatchType::FullMatch { ref provenance, .. }
+            if provenance.origin == MatchOrigin::CountryOverride
+    ));
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::{GeneratedCity, Lcg};
+use location_finder::location_id::CityId;
+use location_finder::spatial::haversine_distance_km;
+
+fn generated_cities(lcg: &mut Lcg, count: usize) -> Vec<GeneratedCity> {
+    (0..count)
+        .map(|index| {
+            // Half the cities cluster around the antimeridian and near the
+            // poles, where a naive longitude/latitude split goes wrong.
+            let (latitude, longitude) = match index % 4 {
+                0 | 1 => (lcg.between(-60.0, 60.0), lcg.between(-180.0, 180.0)),
+                2 => {
+                    let longitude = lcg.between(175.0, 185.0);
+                    let longitude = if longitude >= 180.0 {
+                        longitude - 360.0
+                    } else {
+                        longitude
+                    };
+                    (lcg.between(-20.0, 20.0), longitude)
+                }
+                _ => (lcg.between(80.0, 90.0), lcg.between(-180.0, 180.0)),
+            };
+            GeneratedCity {
+                id: 300000 + index as u64,
+                name: format!("City {}", index),
+                latitude,
+                longitude,
+            }
+        })
+        .collect()
+}
+
+fn brute_force_distances(
+    cities: &[GeneratedCity],
+    latitude: f64,
+    longitude: f64,
+) -> Vec<(CityId, f64)> {
+    let mut distances: Vec<(CityId, f64)> = cities
+        .iter()
+        .map(|city| {
+            (
+                CityId(city.id),
+                haversine_distance_km(latitude, longitude, city.latitude, city.longitude),
+            )
+        })
+        .collect();
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+    distances
+}
+
+fn random_point(lcg: &mut Lcg) -> (f64, f64) {
+    if lcg.below(3) == 0 {
+        (lcg.between(-20.0, 20.0), lcg.between(170.0, 180.0))
+    } else {
+        (lcg.between(-90.0, 90.0), lcg.between(-180.0, 180.0))
+    }
+}
+
+#[test]
+fn nearest_city_agrees_with_brute_force() {
+    let mut lcg = Lcg::new(11);
+    let cities = generated_cities(&mut lcg, 500);
+    let cities_csv = common::generated_cities_csv(&cities);
+    let location_index = common::generated_builder(&cities_csv).build().unwrap();
+    for _ in 0..300 {
+        let (latitude, longitude) = random_point(&mut lcg);
+        let (expected_city, expected_distance) =
+            brute_force_distances(&cities, latitude, longitude)[0];
+        let nearest = location_index.nearest_city(latitude, longitude).unwrap();
+        assert_eq!(
+            nearest.city, expected_city,
+            "at {}, {}",
+            latitude, longitude
+        );
+        assert!((nearest.distance_km - expected_distance).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn cities_within_agrees_with_brute_force() {
+    let mut lcg = Lcg::new(13);
+    let cities = generated_cities(&mut lcg, 500);
+    let cities_csv = common::generated_cities_csv(&cities);
+    let location_index = common::generated_builder(&cities_csv).build().unwrap();
+    let mut found_city_count = 0;
+    for _ in 0..300 {
+        let (latitude, longitude) = random_point(&mut lcg);
+        let radius_km = lcg.between(0.0, 2000.0);
+        let expected: Vec<CityId> = brute_force_distances(&cities, latitude, longitude)
+            .into_iter()
+            .filter(|(_, distance)| *distance <= radius_km)
+            .map(|(city, _)| city)
+            .collect();
+        let found = location_index.cities_within(latitude, longitude, radius_km);
+        assert!(found
+            .windows(2)
+            .all(|pair| pair[0].distance_km <= pair[1].distance_km));
+        let found: Vec<CityId> = found
+            .into_iter()
+            .map(|city_distance| city_distance.city)
+            .collect();
+        assert_eq!(
+            found, expected,
+            "within {} km of {}, {}",
+            radius_km, latitude, longitude
+        );
+        found_city_count += found.len();
+    }
+    assert!(found_city_count > 1000);
+}
+
+#[test]
+fn cities_without_coordinates_are_not_indexed() {
+    let cities_csv = format!(
+        "{}400000,Nowhere,3009,BY,Bavaria,82,DE,Germany,,,Q1\n",
+        common::generated_cities_csv(&[GeneratedCity {
+            id: 400001,
+            name: "Somewhere".to_string(),
+            latitude: 10.0,
+            longitude: 10.0,
+        }])
+    );
+    let location_index = common::generated_builder(&cities_csv).build().unwrap();
+    assert_eq!(
+        location_index.nearest_city(0.0, 0.0).unwrap().city,
+        CityId(400001)
+    );
+    assert_eq!(location_index.cities_within(0.0, 0.0, 20000.0).len(), 1);
+}
+
+#[test]
+fn invalid_coordinates_find_no_cities() {
+    let location_index = common::sample_index();
+    for (latitude, longitude) in [
+        (f64::NAN, 11.0),
+        (48.0, f64::NAN),
+        (f64::INFINITY, 11.0),
+        (48.0, f64::NEG_INFINITY),
+        (90.5, 11.0),
+        (-91.0, 11.0),
+    ] {
+        assert_eq!(
+            location_index.nearest_city(latitude, longitude),
+            None,
+            "at {}, {}",
+            latitude,
+            longitude
+        );
+        assert!(location_index
+            .cities_within(latitude, longitude, 20000.0)
+            .is_empty());
+    }
+    // The poles and longitudes past ±180 are still on the globe.
+    assert!(location_index.nearest_city(90.0, 0.0).is_some());
+    assert!(location_index.nearest_city(-90.0, 0.0).is_some());
+    assert_eq!(
+        location_index
+            .nearest_city(48.13743, 11.57549 + 360.0)
+            .unwrap()
+            .city,
+        CityId(28000)
+    );
+}
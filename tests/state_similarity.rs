@@ -0,0 +1,110 @@
+mod common;
+
+use location_finder::explain::MatchRule;
+use location_finder::location_finder::{LocationMatchType, MatchOrigin};
+use location_finder::location_id::{CityId, StateId};
+use location_finder::location_query::LocationQuery;
+
+#[test]
+fn empty_state_is_not_a_similar_state() {
+    let location_index = common::sample_index();
+    for state in ["", "  ", "-"] {
+        let location_match = location_index.find_location("Munich", state, "DE").unwrap();
+        let LocationMatchType::FullMatch { provenance, .. } = location_match else {
+            panic!("expected a full match, got {:?}", location_match);
+        };
+        assert_eq!(provenance.origin, MatchOrigin::StateMissing);
+    }
+}
+
+#[test]
+fn short_state_is_not_promoted() {
+    let location_index = common::sample_index();
+    for state in ["Ba", "B", "HE"] {
+        let location_match = location_index.find_location("Munich", state, "DE").unwrap();
+        assert!(
+            matches!(
+                location_match,
+                LocationMatchType::PartialMatch {
+                    city: CityId(28000),
+                    unmatched_state: StateId(3009),
+                    ..
+                }
+            ),
+            "{:?} promoted to {:?}",
+            state,
+            location_match
+        );
+    }
+}
+
+#[test]
+fn similar_state_name_is_accepted() {
+    let location_index = common::sample_index();
+    let location_match = location_index
+        .find_location("Frankfurt am Main", "Hessen", "DE")
+        .unwrap();
+    let LocationMatchType::SimilarStateMatch {
+        city,
+        state,
+        similarity,
+        provenance,
+        ..
+    } = location_match
+    else {
+        panic!("expected a similar state match, got {:?}", location_match);
+    };
+    assert_eq!(city, CityId(28001));
+    assert_eq!(state, StateId(3018));
+    assert!(similarity > 0.75 && similarity < 1.0);
+    assert_eq!(provenance.origin, MatchOrigin::StateSimilarity);
+}
+
+#[test]
+fn state_aliases_count_towards_similarity() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index
+            .find_location("Munich", "Bayernn", "DE")
+            .unwrap(),
+        LocationMatchType::SimilarStateMatch {
+            city: CityId(28000),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn dissimilar_state_stays_partial() {
+    let location_index = common::sample_index();
+    assert!(matches!(
+        location_index
+            .find_location("Frankfurt am Main", "Saxony", "DE")
+            .unwrap(),
+        LocationMatchType::PartialMatch {
+            city: CityId(28001),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn similarity_check_is_explained() {
+    let location_index = common::sample_index();
+    let explanation = location_index
+        .explain_location(
+            &LocationQuery::new()
+                .city("Frankfurt am Main")
+                .state("Hessen")
+                .country("DE"),
+        )
+        .unwrap();
+    assert!(explanation.rules.iter().any(|rule| matches!(
+        rule,
+        MatchRule::StateSimilarity {
+            city: CityId(28001),
+            accepted: true,
+            ..
+        }
+    )));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use location_finder::location_finder::LocationMatchType;
+use location_finder::location_id::CityId;
+
+fn candidate_cities(location_match: &LocationMatchType) -> Vec<CityId> {
+    let LocationMatchType::Ambiguous { candidates } = location_match else {
+        panic!("expected an ambiguous match, got {:?}", location_match);
+    };
+    candidates
+        .iter()
+        .map(|candidate| match candidate {
+            LocationMatchType::FullMatch { city, .. } => *city,
+            other => panic!("expected full match candidates, got {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn tied_cities_are_reported_as_ambiguous_in_id_order() {
+    let location_index = common::sample_index();
+    let location_match = location_index.find_location("Neustadt", "", "DE").unwrap();
+    assert_eq!(
+        candidate_cities(&location_match),
+        vec![CityId(28100), CityId(28101)]
+    );
+}
+
+#[test]
+fn resolve_picks_the_lowest_id_every_time() {
+    for _ in 0..5 {
+        let location_index = common::sample_index();
+        let resolved = location_index
+            .find_location("Neustadt", "", "Germany")
+            .unwrap()
+            .resolve();
+        assert!(matches!(
+            resolved,
+            LocationMatchType::FullMatch {
+                city: CityId(28100),
+                ..
+            }
+        ));
+    }
+}
+
+#[test]
+fn resolve_prefers_the_more_specific_match_type() {
+    let location_index = common::sample_index();
+    let city_match = location_index.find_location("Munich", "", "DE").unwrap();
+    let country_match = location_index.find_location("", "", "DE").unwrap();
+    let resolved = LocationMatchType::Ambiguous {
+        candidates: vec![country_match, city_match],
+    }
+    .resolve();
+    assert!(matches!(
+        resolved,
+        LocationMatchType::FullMatch {
+            city: CityId(28000),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn unique_matches_are_unchanged_by_resolve() {
+    let location_index = common::sample_index();
+    let resolved = location_index
+        .find_location("Neustadt", "Hesse", "DE")
+        .unwrap()
+        .resolve();
+    assert!(matches!(
+        resolved,
+        LocationMatchType::FullMatch {
+            city: CityId(28101),
+            ..
+        }
+    ));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use location_finder::location_id::CityId;
+
+// Duplicate records sharing a Wikidata ID, one written in lower case, and
+// cities without one.
+const EXTRA_CITIES_CSV: &str = "\
+27000,Landeshauptstadt München,3009,BY,Bavaria,82,DE,Germany,48.14,11.58,Q1726
+50001,City of London,2336,ENG,England,232,GB,United Kingdom,51.51,-0.09, q84
+28300,Neustadt an der Donau,3009,BY,Bavaria,82,DE,Germany,48.80,11.77,
+28301,Neustadt bei Coburg,3009,BY,Bavaria,82,DE,Germany,50.33,11.12,\" \"
+";
+
+fn wikidata_cities_csv() -> String {
+    format!("{}{}", common::CITIES_CSV, EXTRA_CITIES_CSV)
+}
+
+#[test]
+fn wikidata_lookup_ignores_case_and_whitespace() {
+    let location_index = common::sample_index();
+    let find_city_id = |wikidata_id| {
+        location_index
+            .get_city_by_wikidata(wikidata_id)
+            .map(|city_record| city_record.id)
+    };
+    assert_eq!(find_city_id("Q1726"), Some(CityId(28000)));
+    assert_eq!(find_city_id("q1726"), Some(CityId(28000)));
+    assert_eq!(find_city_id(" Q64 "), Some(CityId(28002)));
+    assert_eq!(find_city_id("Q999999"), None);
+    assert_eq!(find_city_id(""), None);
+}
+
+#[test]
+fn shared_wikidata_ids_resolve_to_the_lowest_city_id() {
+    let cities_csv = wikidata_cities_csv();
+    let location_index = common::sample_builder()
+        .cities(cities_csv.as_bytes())
+        .build()
+        .unwrap();
+    let find_city_id = |wikidata_id| {
+        location_index
+            .get_city_by_wikidata(wikidata_id)
+            .map(|city_record| city_record.id)
+    };
+    assert_eq!(find_city_id("Q1726"), Some(CityId(27000)));
+    assert_eq!(find_city_id("Q84"), Some(CityId(50000)));
+}
+
+#[test]
+fn wikidata_report_lists_shared_and_missing_ids() {
+    let location_index = common::sample_index();
+    let wikidata_report = location_index.wikidata_report();
+    assert!(wikidata_report.shared_wikidata_ids.is_empty());
+    assert!(wikidata_report.missing_wikidata_ids.is_empty());
+
+    let cities_csv = wikidata_cities_csv();
+    let location_index = common::sample_builder()
+        .cities(cities_csv.as_bytes())
+        .build()
+        .unwrap();
+    let wikidata_report = location_index.wikidata_report();
+    let shared_wikidata_ids: Vec<(&str, &[CityId])> = wikidata_report
+        .shared_wikidata_ids
+        .iter()
+        .map(|shared| (shared.wikidata_id.as_str(), shared.cities.as_slice()))
+        .collect();
+    assert_eq!(
+        shared_wikidata_ids,
+        vec![
+            ("Q1726", &[CityId(27000), CityId(28000)][..]),
+            ("Q84", &[CityId(50000), CityId(50001)][..]),
+        ]
+    );
+    assert_eq!(
+        wikidata_report.missing_wikidata_ids,
+        vec![CityId(28300), CityId(28301)]
+    );
+}